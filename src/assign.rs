@@ -25,6 +25,11 @@ impl VarAssign {
         self.v.reserve(var)
     }
 
+    #[inline]
+    pub fn max_var(&self) -> Var {
+        self.v.max_var()
+    }
+
     #[inline]
     pub fn v(&self, lit: Lit) -> Lbool {
         Lbool(self.v[lit].0 ^ (!lit.polarity() as u8))
@@ -1,4 +1,8 @@
-use crate::{DagCnf, Lit, LitVec, LitVvec, Var, VarVMap};
+mod simplify;
+
+pub use simplify::*;
+
+use crate::{DagCnf, Lit, LitMap, LitVec, LitVvec, Var, VarVMap};
 use giputils::hash::GHashSet;
 use std::{
     iter::once,
@@ -166,4 +170,56 @@ impl DagCnf {
             cls: self.clause().cloned().collect(),
         }
     }
+
+    /// Plaisted-Greenbaum lowering. `roots` are the literals that will be
+    /// asserted; for every var only the half of its relation needed by the
+    /// polarities in which it is used is emitted. With `cone`, vars outside
+    /// the cone of `roots` are dropped, otherwise their relations are kept in
+    /// full.
+    pub fn lower_pol(&self, roots: impl IntoIterator<Item = impl Into<Lit>>, cone: bool) -> Cnf {
+        let mut need: LitMap<bool> = LitMap::new_with(self.max_var());
+        for r in roots.into_iter().map(|r| r.into()) {
+            need[r] = true;
+        }
+        let mut cls = vec![LitVec::from([Lit::constant(true)])];
+        for v in self.var_iter_woc().rev() {
+            let (p, n) = (v.lit(), !v.lit());
+            if !cone && !need[p] && !need[n] {
+                need[p] = true;
+                need[n] = true;
+            }
+            for c in self[v].iter() {
+                if need[!c.last()] {
+                    for &l in c[..c.len() - 1].iter() {
+                        need[l] = true;
+                    }
+                    cls.push(c.clone());
+                }
+            }
+        }
+        Cnf {
+            max_var: self.max_var(),
+            cls,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{DagCnf, Lit, Var};
+
+    #[test]
+    fn test_lower_pol() {
+        let mut dc = DagCnf::new();
+        dc.new_var_to(Var(3));
+        let x = dc.new_and([Lit::from(1), Lit::from(2)]);
+        let y = dc.new_or([x, Lit::from(3)]);
+        let z = dc.new_and([Lit::from(2), Lit::from(3)]);
+        assert_eq!(dc.lower().len(), 10);
+        assert_eq!(dc.lower_pol([y], true).len(), 4);
+        assert_eq!(dc.lower_pol([!y], true).len(), 4);
+        assert_eq!(dc.lower_pol([y, !y], true).len(), 7);
+        assert_eq!(dc.lower_pol([y], false).len(), 7);
+        assert_eq!(dc.lower_pol([z], true).len(), 3);
+    }
 }
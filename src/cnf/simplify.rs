@@ -0,0 +1,556 @@
+use super::Cnf;
+use crate::{Lit, LitOrdVec, LitVec, ReconstructStack, Var, VarAssign, VarRange, occur::Occurs};
+use giputils::{allocator::Gallocator, hash::GHashSet, heap::BinaryHeap, ptr::Grc};
+use log::debug;
+use std::{
+    iter::once,
+    time::{Duration, Instant},
+};
+
+#[derive(Clone, Debug)]
+pub struct CnfSimplifyConfig {
+    /// Time budget of all phases together.
+    pub time_limit: Option<Duration>,
+    /// Number of clauses BVE may add over the number it removes.
+    pub max_clause_growth: usize,
+    pub max_resolvent_len: usize,
+    /// Literals with more occurrences than this are not used as pivot by
+    /// BVE, BCE and CCE.
+    pub max_occur: usize,
+    pub subsume: bool,
+    pub bve: bool,
+    pub bce: bool,
+    pub cce: bool,
+}
+
+impl Default for CnfSimplifyConfig {
+    fn default() -> Self {
+        Self {
+            time_limit: None,
+            max_clause_growth: 0,
+            max_resolvent_len: usize::MAX,
+            max_occur: 2000,
+            subsume: true,
+            bve: true,
+            bce: true,
+            cce: true,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct CnfSimplifyStats {
+    pub num_units: usize,
+    pub num_eliminated: usize,
+    pub num_blocked: usize,
+    pub num_covered: usize,
+    pub num_removed_cls: usize,
+    pub num_strengthened_cls: usize,
+    pub time: Duration,
+}
+
+pub struct CnfSimplify {
+    cdb: Grc<Gallocator<LitOrdVec>>,
+    max_var: Var,
+    cls: Vec<usize>,
+    occur: Grc<Occurs<LitOrdVec>>,
+    qbve: BinaryHeap<Var, Occurs<LitOrdVec>>,
+    frozen: GHashSet<Var>,
+    value: VarAssign,
+    units: Vec<Lit>,
+    unsat: bool,
+    rstack: ReconstructStack,
+    num_ocls: usize,
+    config: CnfSimplifyConfig,
+    stats: CnfSimplifyStats,
+}
+
+impl CnfSimplify {
+    pub fn new(cnf: &Cnf) -> Self {
+        let max_var = cnf.max_var;
+        let cdb = Grc::new(Gallocator::new());
+        let occur = Grc::new(Occurs::new_with(max_var, cdb.clone()));
+        let qbve = BinaryHeap::new(occur.clone());
+        let mut res = Self {
+            cdb,
+            max_var,
+            cls: Vec::new(),
+            occur,
+            qbve,
+            frozen: GHashSet::from_iter([Var::CONST]),
+            value: VarAssign::new_with(max_var),
+            units: Vec::new(),
+            unsat: false,
+            rstack: ReconstructStack::new(),
+            num_ocls: cnf.len(),
+            config: CnfSimplifyConfig::default(),
+            stats: CnfSimplifyStats::default(),
+        };
+        for cls in cnf.iter() {
+            res.add_clause(cls.clone());
+        }
+        res
+    }
+
+    #[inline]
+    pub fn froze(&mut self, v: Var) {
+        self.frozen.insert(v);
+    }
+
+    #[inline]
+    pub fn set_config(&mut self, config: CnfSimplifyConfig) {
+        self.config = config;
+    }
+
+    #[inline]
+    pub fn stats(&self) -> &CnfSimplifyStats {
+        &self.stats
+    }
+
+    /// Whether the empty clause has been derived.
+    #[inline]
+    pub fn is_unsat(&self) -> bool {
+        self.unsat
+    }
+
+    #[inline]
+    fn out_of_time(&self, start: Instant) -> bool {
+        self.config
+            .time_limit
+            .is_some_and(|l| self.stats.time + start.elapsed() > l)
+    }
+
+    fn add_clause(&mut self, mut cls: LitVec) {
+        cls.sort();
+        let Some(cls) = cls.ordered_simp(&self.value) else {
+            return;
+        };
+        match cls.len() {
+            0 => self.unsat = true,
+            1 => {
+                self.value.set(cls[0]);
+                self.units.push(cls[0]);
+            }
+            _ => {
+                let ci = self.cdb.alloc(LitOrdVec::new(cls));
+                for &l in self.cdb[ci].iter() {
+                    self.occur.add(l, ci);
+                    self.qbve.down(l.var());
+                }
+                self.cls.push(ci);
+            }
+        }
+    }
+
+    fn remove_clause(&mut self, ci: usize) {
+        for &l in self.cdb[ci].iter() {
+            self.occur.del(l, ci);
+            self.qbve.up(l.var());
+        }
+        self.cdb.dealloc(ci);
+    }
+
+    #[inline]
+    fn clause_ids(&mut self) -> Vec<usize> {
+        self.cls.retain(|&c| !self.cdb.is_removed(c));
+        self.cls.clone()
+    }
+
+    #[inline]
+    fn is_elim_candidate(&self, l: Lit) -> bool {
+        !self.frozen.contains(&l.var()) && self.occur.num_occur(!l) <= self.config.max_occur
+    }
+
+    /// Propagate the pending units, removing satisfied clauses and false
+    /// literals.
+    pub fn propagate(&mut self) {
+        while let Some(l) = self.units.pop() {
+            if self.unsat {
+                return;
+            }
+            self.stats.num_units += 1;
+            self.rstack.push_assign(l);
+            for c in self.occur.get(l).to_vec() {
+                self.remove_clause(c);
+                self.stats.num_removed_cls += 1;
+            }
+            for c in self.occur.get(!l).to_vec() {
+                let cls = self.cdb[c].as_litvec().clone();
+                self.remove_clause(c);
+                self.stats.num_strengthened_cls += 1;
+                self.add_clause(cls);
+            }
+        }
+    }
+
+    fn cls_subsume_check(&mut self, ci: usize) {
+        let best_lit = *self.cdb[ci]
+            .iter()
+            .min_by_key(|&&l| self.occur.num_occur(l) + self.occur.num_occur(!l))
+            .unwrap();
+        let mut occurs = self.occur.get(best_lit).to_vec();
+        occurs.extend_from_slice(self.occur.get(!best_lit));
+        for cj in occurs {
+            if cj == ci || self.cdb.is_removed(cj) {
+                continue;
+            }
+            let (res, diff) = self.cdb[ci].subsume_execpt_one(&self.cdb[cj]);
+            if res {
+                self.remove_clause(cj);
+                self.stats.num_removed_cls += 1;
+            } else if let Some(diff) = diff {
+                let same = self.cdb[ci].len() == self.cdb[cj].len();
+                let mut cls = self.cdb[cj].as_litvec().clone();
+                cls.retain(|l| *l != !diff);
+                self.remove_clause(cj);
+                self.stats.num_strengthened_cls += 1;
+                if same {
+                    self.remove_clause(ci);
+                    self.stats.num_removed_cls += 1;
+                    self.add_clause(cls);
+                    return;
+                }
+                self.add_clause(cls);
+            }
+        }
+    }
+
+    /// Subsumption and self-subsuming resolution.
+    pub fn subsume_simplify(&mut self) {
+        let start = Instant::now();
+        let mut i = 0;
+        self.cls.retain(|&c| !self.cdb.is_removed(c));
+        while i < self.cls.len() && !self.unsat && !self.out_of_time(start) {
+            let ci = self.cls[i];
+            i += 1;
+            if !self.cdb.is_removed(ci) {
+                self.cls_subsume_check(ci);
+            }
+            self.propagate();
+        }
+        self.stats.time += start.elapsed();
+    }
+
+    fn eliminate(&mut self, v: Var) {
+        if self.frozen.contains(&v) || !self.value.v(v.lit()).is_none() {
+            return;
+        }
+        let lv = v.lit();
+        let pos = self.occur.get(lv).to_vec();
+        let neg = self.occur.get(!lv).to_vec();
+        let ocost = pos.len() + neg.len();
+        if ocost == 0 || ocost > self.config.max_occur {
+            return;
+        }
+        let limit = ocost + self.config.max_clause_growth;
+        let mut res = Vec::new();
+        for &p in pos.iter() {
+            for &n in neg.iter() {
+                if let Some(r) = self.cdb[p].ordered_resolvent(&self.cdb[n], v) {
+                    if r.len() > self.config.max_resolvent_len {
+                        return;
+                    }
+                    res.push(r);
+                    if res.len() > limit {
+                        return;
+                    }
+                }
+            }
+        }
+        let ecls = pos
+            .iter()
+            .chain(neg.iter())
+            .map(|&c| self.cdb[c].as_litvec().clone())
+            .collect();
+        self.rstack.push_eliminate(v, ecls);
+        for c in pos.into_iter().chain(neg) {
+            self.remove_clause(c);
+            self.stats.num_removed_cls += 1;
+        }
+        for r in res {
+            self.add_clause(r);
+        }
+        self.stats.num_eliminated += 1;
+    }
+
+    /// Bounded variable elimination by clause distribution.
+    pub fn bve_simplify(&mut self) {
+        let start = Instant::now();
+        for v in VarRange::new_inclusive(Var(1), self.max_var) {
+            self.qbve.push(v);
+        }
+        while let Some(v) = self.qbve.pop() {
+            if self.unsat || self.out_of_time(start) {
+                break;
+            }
+            self.eliminate(v);
+            self.propagate();
+        }
+        self.stats.time += start.elapsed();
+    }
+
+    /// Whether every resolvent of `cls` on `l` is a tautology.
+    fn is_blocked(&mut self, cls: &LitVec, l: Lit, ci: usize) -> bool {
+        let cls = LitOrdVec::new(cls.clone());
+        for &d in self.occur.get(!l).iter() {
+            if d != ci && self.cdb[d].ordered_resolvent(&cls, l.var()).is_some() {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Blocked clause elimination.
+    pub fn bce_simplify(&mut self) {
+        let start = Instant::now();
+        for ci in self.clause_ids() {
+            if self.out_of_time(start) {
+                break;
+            }
+            if self.cdb.is_removed(ci) {
+                continue;
+            }
+            let cls = self.cdb[ci].as_litvec().clone();
+            let blocked = cls
+                .iter()
+                .copied()
+                .find(|&l| self.is_elim_candidate(l) && self.is_blocked(&cls, l, ci));
+            if let Some(l) = blocked {
+                self.rstack.push_witness(l, cls);
+                self.remove_clause(ci);
+                self.stats.num_blocked += 1;
+                self.stats.num_removed_cls += 1;
+            }
+        }
+        self.stats.time += start.elapsed();
+    }
+
+    /// Extend `cls` by covered literal addition. Returns the extension steps
+    /// with their witnesses, and the blocking literal with the extended clause
+    /// if it is blocked, or None if it is a tautology. The blocking literal
+    /// may be one of the added ones. Returns Err if the clause is not covered.
+    #[allow(clippy::type_complexity)]
+    fn covered(&mut self, ci: usize) -> Result<(Vec<(LitVec, Lit)>, Option<(Lit, LitVec)>), ()> {
+        let mut ext = self.cdb[ci].as_litvec().clone();
+        let mut steps = Vec::new();
+        'ext: loop {
+            for i in 0..ext.len() {
+                let l = ext[i];
+                if !self.is_elim_candidate(l) {
+                    continue;
+                }
+                let cls = LitOrdVec::new(ext.clone());
+                let mut common: Option<LitVec> = None;
+                for &d in self.occur.get(!l).iter() {
+                    if d == ci || self.cdb[d].ordered_resolvent(&cls, l.var()).is_none() {
+                        continue;
+                    }
+                    let rest = self.cdb[d].filter(|x| x != !l);
+                    common = Some(match common {
+                        None => rest,
+                        Some(c) => c.ordered_intersection(&rest),
+                    });
+                }
+                let Some(common) = common else {
+                    return Ok((steps, Some((l, ext))));
+                };
+                let add: LitVec = common
+                    .iter()
+                    .filter(|x| !ext.contains(x))
+                    .copied()
+                    .collect();
+                if add.is_empty() {
+                    continue;
+                }
+                steps.push((ext.clone(), l));
+                if add.iter().any(|x| ext.contains(&!*x)) {
+                    return Ok((steps, None));
+                }
+                ext.extend(add);
+                ext.sort();
+                continue 'ext;
+            }
+            return Err(());
+        }
+    }
+
+    /// Covered clause elimination, removes clauses whose extension by covered
+    /// literal addition is blocked or a tautology.
+    pub fn cce_simplify(&mut self) {
+        let start = Instant::now();
+        for ci in self.clause_ids() {
+            if self.out_of_time(start) {
+                break;
+            }
+            if self.cdb.is_removed(ci) {
+                continue;
+            }
+            let Ok((steps, blocked)) = self.covered(ci) else {
+                continue;
+            };
+            for (c, l) in steps {
+                self.rstack.push_witness(l, c);
+            }
+            if let Some((l, ext)) = blocked {
+                self.rstack.push_witness(l, ext);
+            }
+            self.remove_clause(ci);
+            self.stats.num_covered += 1;
+            self.stats.num_removed_cls += 1;
+        }
+        self.stats.time += start.elapsed();
+    }
+
+    pub fn finalize(&mut self) -> Cnf {
+        let mut cnf = Cnf::new();
+        cnf.new_var_to(self.max_var);
+        if self.unsat {
+            cnf.add_clause(&[Lit::constant(false)]);
+            return cnf;
+        }
+        for v in VarRange::new_inclusive(Var(1), self.max_var) {
+            if self.frozen.contains(&v)
+                && let Some(vl) = self.value.vl(v)
+            {
+                cnf.add_clause(&[vl]);
+            }
+        }
+        for ci in self.clause_ids() {
+            cnf.add_clause(self.cdb[ci].as_litvec());
+        }
+        debug!(
+            "cnf simplified from {} to {} clauses in {:.2}s, {} vars eliminated",
+            self.num_ocls,
+            cnf.len(),
+            self.stats.time.as_secs_f64(),
+            self.stats.num_eliminated
+        );
+        cnf
+    }
+
+    /// Extend `assign`, a model of the simplified `Cnf`, to a model of the
+    /// original one.
+    pub fn reconstruct(&self, assign: &VarAssign) -> VarAssign {
+        let mut assign = assign.clone();
+        assign.reserve(self.max_var);
+        self.rstack.extend(&mut assign);
+        assign
+    }
+
+    #[inline]
+    pub fn reconstruct_stack(&self) -> &ReconstructStack {
+        &self.rstack
+    }
+
+    pub fn simplify(&mut self) -> Cnf {
+        self.propagate();
+        if self.config.subsume {
+            self.subsume_simplify();
+        }
+        if self.config.bve {
+            self.bve_simplify();
+        }
+        if self.config.bce {
+            self.bce_simplify();
+        }
+        if self.config.cce {
+            self.cce_simplify();
+        }
+        self.finalize()
+    }
+}
+
+impl Cnf {
+    /// Simplify the clauses while keeping the `frozen` vars, see
+    /// `CnfSimplify` for model reconstruction.
+    pub fn simplify(&self, frozen: impl IntoIterator<Item = impl Into<Var>>) -> Self {
+        let mut simp = CnfSimplify::new(self);
+        for v in frozen.into_iter().map(|l| l.into()).chain(once(Var::CONST)) {
+            simp.froze(v);
+        }
+        simp.simplify()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::CnfSimplify;
+    use crate::{Cnf, Lit, LitVec, Var, VarAssign};
+
+    fn satisfied(cnf: &Cnf, assign: &VarAssign) -> bool {
+        cnf.iter()
+            .all(|cls| cls.iter().any(|&l| assign.v(l).is_true()))
+    }
+
+    #[test]
+    fn test_cnf_simplify() {
+        let mut cnf = Cnf::new();
+        cnf.new_var_to(Var(4));
+        let (a, b, c) = (Lit::from(1), Lit::from(2), Lit::from(3));
+        let x = cnf.new_and([a, b]);
+        let y = cnf.new_or([x, c]);
+        cnf.add_clause(&[a, b, Lit::from(4)]);
+        cnf.add_clause(&[a, b]);
+        cnf.add_clause(&[y]);
+        let mut simp = CnfSimplify::new(&cnf);
+        simp.froze(Var(3));
+        let scnf = simp.simplify();
+        assert!(!simp.is_unsat());
+        assert!(scnf.len() < cnf.len());
+        assert!(scnf.iter().all(|cls| cls.iter().all(|l| l.var() <= Var(3))));
+        for nc in [true, false] {
+            let mut assign = VarAssign::new_with(scnf.max_var());
+            assign.set(Lit::constant(true));
+            assign.set(c.not_if(!nc));
+            if !satisfied(&scnf, &assign) {
+                continue;
+            }
+            let assign = simp.reconstruct(&assign);
+            assert!(satisfied(&cnf, &assign));
+        }
+    }
+
+    #[test]
+    fn test_cce_covered_witness() {
+        let mut cnf = Cnf::new();
+        cnf.new_var_to(Var(8));
+        let [a, b, x, w, y, p, q, z] = [1, 2, 3, 4, 5, 6, 7, 8].map(Lit::from);
+        cnf.add_clause(&[a, b]);
+        cnf.add_clause(&[!a, x, w]);
+        cnf.add_clause(&[!a, x, w, y]);
+        cnf.add_clause(&[!b, p]);
+        cnf.add_clause(&[!b, q]);
+        cnf.add_clause(&[!x, !w, z]);
+        let mut simp = CnfSimplify::new(&cnf);
+        for v in [y, p, q, z] {
+            simp.froze(v.var());
+        }
+        simp.cce_simplify();
+        let scnf = simp.finalize();
+        assert!(scnf.len() < cnf.len());
+        for m in 0..1usize << 8 {
+            let mut assign = VarAssign::new_with(scnf.max_var());
+            assign.set(Lit::constant(true));
+            for i in 0..8 {
+                assign.set(Lit::from(i + 1).not_if(m >> i & 1 == 0));
+            }
+            if !satisfied(&scnf, &assign) {
+                continue;
+            }
+            let assign = simp.reconstruct(&assign);
+            assert!(satisfied(&cnf, &assign));
+        }
+    }
+
+    #[test]
+    fn test_cnf_unsat() {
+        let mut cnf = Cnf::new();
+        cnf.new_var_to(Var(2));
+        let (a, b) = (Lit::from(1), Lit::from(2));
+        for cls in [[a, b], [a, !b], [!a, b], [!a, !b]] {
+            cnf.add_clause(&cls);
+        }
+        let scnf = cnf.simplify([Var(1)]);
+        assert!(scnf.contains(&LitVec::from([Lit::constant(false)])));
+    }
+}
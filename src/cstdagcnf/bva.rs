@@ -1,15 +1,57 @@
 use crate::{Cnf, CstDagCnf, DagCnf, Lit, LitMap, LitOrdVec, LitVec, Var, VarRange, occur::Occurs};
 use giputils::{allocator::Gallocator, ptr::Grc};
+use log::debug;
 use std::{
     cmp::Ordering,
     collections::{BinaryHeap, HashMap, HashSet},
+    time::{Duration, Instant},
 };
 
+#[derive(Clone, Debug)]
+pub struct BVAConfig {
+    pub time_limit: Option<Duration>,
+    /// Max number of vars introduced.
+    pub max_introduced: usize,
+    /// Max number of literals taken from the queue.
+    pub max_steps: usize,
+}
+
+impl Default for BVAConfig {
+    fn default() -> Self {
+        Self {
+            time_limit: None,
+            max_introduced: usize::MAX,
+            max_steps: usize::MAX,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct BVAStats {
+    pub num_steps: usize,
+    pub num_introduced: usize,
+    /// Clauses replaced by the introduced vars.
+    pub num_removed_cls: usize,
+    /// Clauses added, including the relations of the introduced vars.
+    pub num_added_cls: usize,
+    pub time: Duration,
+}
+
+impl BVAStats {
+    /// Net number of clauses saved.
+    #[inline]
+    pub fn reduction(&self) -> isize {
+        self.num_removed_cls as isize - self.num_added_cls as isize
+    }
+}
+
 pub struct BVA {
     cdb: Grc<Gallocator<LitOrdVec>>,
     occur: Occurs<LitOrdVec>,
     dc: DagCnf,
     lit_count_adjust: LitMap<usize>,
+    config: BVAConfig,
+    stats: BVAStats,
 }
 
 impl BVA {
@@ -25,6 +67,8 @@ impl BVA {
             occur,
             dc,
             lit_count_adjust,
+            config: BVAConfig::default(),
+            stats: BVAStats::default(),
         };
         for cls in cnf.clauses() {
             res.add_clause(cls);
@@ -32,6 +76,11 @@ impl BVA {
         res
     }
 
+    #[inline]
+    pub fn set_config(&mut self, config: BVAConfig) {
+        self.config = config;
+    }
+
     fn reserve(&mut self, var: Var) {
         assert!(self.dc.max_var() >= var);
         self.occur.reserve(var);
@@ -67,7 +116,14 @@ impl BVA {
             .cloned()
     }
 
-    pub fn bva(mut self) -> CstDagCnf {
+    pub fn bva(self) -> CstDagCnf {
+        self.bva_with_stats().0
+    }
+
+    /// Run BVA within the budgets of the config. The introduced vars are the
+    /// non-leaf vars of the result, see `CstDagCnf::introduced_vars`.
+    pub fn bva_with_stats(mut self) -> (CstDagCnf, BVAStats) {
+        let start = Instant::now();
         let mut queue = BinaryHeap::new();
         for v in VarRange::new_inclusive(Var::CONST, self.dc.max_var()) {
             let l = v.lit();
@@ -75,6 +131,13 @@ impl BVA {
             queue.push(QueueElement(!l, self.lit_count(!l)));
         }
         while let Some(QueueElement(max_lit, num_cls)) = queue.pop() {
+            if self.stats.num_steps >= self.config.max_steps
+                || self.stats.num_introduced >= self.config.max_introduced
+                || self.config.time_limit.is_some_and(|l| start.elapsed() > l)
+            {
+                break;
+            }
+            self.stats.num_steps += 1;
             if num_cls == 0 || num_cls != self.lit_count(max_lit) {
                 continue;
             }
@@ -144,6 +207,9 @@ impl BVA {
             {
                 continue;
             }
+            self.stats.num_introduced += 1;
+            self.stats.num_added_cls += matched_lits.len() + 1 + matched_clauses.len();
+            self.stats.num_removed_cls += matched_lits.len() * matched_clauses.len();
             let nl = self.dc.new_and(matched_lits);
             for mcls in matched_clauses.iter() {
                 let mut cls = LitVec::from([nl]);
@@ -185,7 +251,14 @@ impl BVA {
                 }
             });
         }
-        CstDagCnf { dag: self.dc, cst }
+        self.stats.time = start.elapsed();
+        debug!(
+            "bva introduced {} vars, saving {} clauses in {:.2}s",
+            self.stats.num_introduced,
+            self.stats.reduction(),
+            self.stats.time.as_secs_f64()
+        );
+        (CstDagCnf { dag: self.dc, cst }, self.stats)
     }
 }
 
@@ -241,4 +314,26 @@ mod tests {
         let bva = BVA::new(cnf);
         dbg!(bva.bva());
     }
+
+    #[test]
+    fn test_lower() {
+        let mut cnf = Cnf::new();
+        for x in [1, 2, 3] {
+            for y in [4, 5, 6] {
+                cnf.add_clause(&[Lit::from(x), Lit::from(y)]);
+            }
+        }
+        let (res, stats) = BVA::new(cnf.clone()).bva_with_stats();
+        assert_eq!(stats.num_introduced, 1);
+        assert_eq!(stats.reduction(), 2);
+        assert_eq!(res.introduced_vars(), vec![Var(7)]);
+        let lowered = res.lower();
+        assert_eq!(lowered.len(), cnf.len() - 2);
+        let mut bva = BVA::new(cnf);
+        bva.set_config(BVAConfig {
+            max_introduced: 0,
+            ..Default::default()
+        });
+        assert!(bva.bva().introduced_vars().is_empty());
+    }
 }
@@ -1,6 +1,6 @@
 mod bva;
 
-use crate::{Cnf, DagCnf};
+use crate::{Cnf, DagCnf, Lit, Var};
 pub use bva::*;
 use std::ops::Deref;
 
@@ -18,3 +18,27 @@ impl Deref for CstDagCnf {
         &self.dag
     }
 }
+
+impl CstDagCnf {
+    /// Vars introduced by the relations of `dag`, to be projected out of
+    /// models of the lowered `Cnf`.
+    pub fn introduced_vars(&self) -> Vec<Var> {
+        self.dag
+            .var_iter_woc()
+            .filter(|&v| !self.dag.is_leaf(v))
+            .collect()
+    }
+
+    /// Lower to a `Cnf` holding the relations of `dag` and the constraints,
+    /// equisatisfiable with the input of `BVA`.
+    pub fn lower(&self) -> Cnf {
+        let mut cnf = self.dag.lower();
+        cnf.new_var_to(self.cst.max_var());
+        for cls in self.cst.iter() {
+            if !(cls.len() == 1 && cls[0] == Lit::constant(true)) {
+                cnf.add_clause(cls);
+            }
+        }
+        cnf
+    }
+}
@@ -0,0 +1,137 @@
+use super::DagCnf;
+use crate::{Var, VarMap, VarVMap};
+
+impl DagCnf {
+    fn cone_vars(
+        &self,
+        roots: impl IntoIterator<Item = impl Into<Var>>,
+        stamp: &mut VarMap<usize>,
+        s: usize,
+    ) -> Vec<Var> {
+        let mut cone = vec![Var::CONST];
+        stamp[Var::CONST] = s;
+        let mut queue = Vec::new();
+        for r in roots.into_iter().map(|r| r.into()) {
+            if stamp[r] != s {
+                stamp[r] = s;
+                queue.push(r);
+            }
+        }
+        while let Some(v) = queue.pop() {
+            cone.push(v);
+            for &d in self.dep[v].iter() {
+                if stamp[d] != s {
+                    stamp[d] = s;
+                    queue.push(d);
+                }
+            }
+        }
+        cone.sort();
+        cone
+    }
+
+    fn cone_extract(&self, cone: &[Var]) -> (DagCnf, VarVMap) {
+        let mut map = VarVMap::new();
+        let mut res = DagCnf::new();
+        for (i, &v) in cone.iter().enumerate() {
+            map.insert(v, Var::new(i));
+        }
+        res.new_var_to(Var::new(cone.len() - 1));
+        for &v in cone.iter().skip(1) {
+            let rel: Vec<_> = self.cnf[v]
+                .iter()
+                .map(|cls| cls.map_var(|x| map[x]))
+                .collect();
+            res.add_rel(map[v], &rel);
+        }
+        (res, map)
+    }
+
+    /// Extract the cone of influence of `roots` into a compactly numbered
+    /// `DagCnf`, preserving the relative order of vars. The returned map goes
+    /// from old to new vars; symbols can follow it with
+    /// `symbols.map_var(map.try_map_fn())`.
+    pub fn extract_cone(
+        &self,
+        roots: impl IntoIterator<Item = impl Into<Var>>,
+    ) -> (DagCnf, VarVMap) {
+        let mut stamp = VarMap::new_with(self.max_var);
+        let cone = self.cone_vars(roots, &mut stamp, 1);
+        self.cone_extract(&cone)
+    }
+
+    /// Extract the cone of influence of each group of roots. The union of
+    /// the cones is traversed once, and the group memberships are pushed
+    /// from the roots down to their deps in one reverse topological pass.
+    pub fn extract_cones(
+        &self,
+        roots: impl IntoIterator<Item = impl IntoIterator<Item = impl Into<Var>>>,
+    ) -> Vec<(DagCnf, VarVMap)> {
+        let groups: Vec<Vec<Var>> = roots
+            .into_iter()
+            .map(|r| r.into_iter().map(|v| v.into()).collect())
+            .collect();
+        let words = groups.len().div_ceil(64);
+        let mut stamp = VarMap::new_with(self.max_var);
+        let union = self.cone_vars(groups.iter().flatten().copied(), &mut stamp, 1);
+        let mut member: VarMap<Vec<u64>> = VarMap::new_with(self.max_var);
+        for &v in union.iter() {
+            member[v] = vec![0; words];
+        }
+        for (g, r) in groups.iter().enumerate() {
+            for &v in r.iter() {
+                member[v][g / 64] |= 1 << (g % 64);
+            }
+        }
+        for &v in union.iter().rev() {
+            for &d in self.dep[v].iter() {
+                for w in 0..words {
+                    let m = member[v][w];
+                    member[d][w] |= m;
+                }
+            }
+        }
+        let mut cones = vec![vec![Var::CONST]; groups.len()];
+        for &v in union.iter().skip(1) {
+            for (w, &m) in member[v].iter().enumerate() {
+                let mut m = m;
+                while m != 0 {
+                    cones[w * 64 + m.trailing_zeros() as usize].push(v);
+                    m &= m - 1;
+                }
+            }
+        }
+        cones.iter().map(|c| self.cone_extract(c)).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{DagCnf, Lit, Var, VarSymbols, gate::Gate};
+
+    #[test]
+    fn test_extract_cone() {
+        let mut dc = DagCnf::new();
+        dc.new_var_to(Var(4));
+        let x = dc.new_and([Lit::from(1), Lit::from(3)]);
+        let y = dc.new_or([Lit::from(2), Lit::from(4)]);
+        let z = dc.new_xor(x, Lit::from(-4));
+        let mut symbols = VarSymbols::new();
+        symbols.insert(Var(4), "a".to_string(), 0);
+        symbols.insert(y.var(), "y".to_string(), 0);
+        let cones = dc.extract_cones([vec![z.var()], vec![y.var()]]);
+        let (cdc, map) = &cones[0];
+        assert_eq!(cdc.max_var(), Var(5));
+        assert_eq!(map[z.var()], Var(5));
+        assert_eq!(
+            cdc.gate(Var(5)),
+            Some(Gate::Xnor(Var(3).lit(), Var(4).lit()))
+        );
+        let (cdc, map) = &cones[1];
+        assert_eq!(cdc.max_var(), Var(3));
+        let csymbols = symbols.map_var(map.try_map_fn());
+        assert_eq!(csymbols.get(Var(2)), vec![("a".to_string(), 0)]);
+        assert_eq!(csymbols.get(Var(3)), vec![("y".to_string(), 0)]);
+        assert_eq!(dc.extract_cone([y]).0.num_clause(), cdc.num_clause());
+    }
+}
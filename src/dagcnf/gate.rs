@@ -0,0 +1,304 @@
+use super::DagCnf;
+use crate::{Lit, LitVec, Var, VarMap};
+
+/// Max support size for which a relation is classified by truth table.
+pub(crate) const GATE_TT_MAX_SUPPORT: usize = 6;
+
+/// Typed view of the relation defining a var. The var (in positive polarity)
+/// equals the gate applied to the operands.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Gate {
+    And(LitVec),
+    Or(LitVec),
+    /// Operands are always positive literals.
+    Xor(Lit, Lit),
+    /// Operands are always positive literals.
+    Xnor(Lit, Lit),
+    /// (cond, then, else), cond is always a positive literal.
+    Ite(Lit, Lit, Lit),
+    Majority(Lit, Lit, Lit),
+    Equiv(Lit),
+    Unknown,
+}
+
+impl Gate {
+    #[inline]
+    pub fn is_unknown(&self) -> bool {
+        matches!(self, Gate::Unknown)
+    }
+
+    pub fn inputs(&self) -> LitVec {
+        match self {
+            Gate::And(l) | Gate::Or(l) => l.clone(),
+            Gate::Xor(x, y) | Gate::Xnor(x, y) => LitVec::from([*x, *y]),
+            Gate::Ite(x, y, z) | Gate::Majority(x, y, z) => LitVec::from([*x, *y, *z]),
+            Gate::Equiv(l) => LitVec::from([*l]),
+            Gate::Unknown => LitVec::new(),
+        }
+    }
+}
+
+#[inline]
+fn tt_full(k: usize) -> u64 {
+    if k == GATE_TT_MAX_SUPPORT {
+        u64::MAX
+    } else {
+        (1 << (1 << k)) - 1
+    }
+}
+
+#[inline]
+fn tt_var(i: usize, k: usize) -> u64 {
+    let mut m = 0;
+    for a in 0..1 << k {
+        if a >> i & 1 == 1 {
+            m |= 1 << a;
+        }
+    }
+    m
+}
+
+impl DagCnf {
+    /// Truth table of the relation of `v` over `dep`, bit `a` holds the value
+    /// of `v` under the assignment whose i-th bit is the value of `dep[i]`.
+    /// Literals of the constant var are evaluated, so `dep` must not contain
+    /// it. Returns None if the relation is not a total function of `dep`.
+    pub(crate) fn truth_table(&self, v: Var, dep: &[Var]) -> Option<u64> {
+        let k = dep.len();
+        debug_assert!(k <= GATE_TT_MAX_SUPPORT);
+        let (mut on, mut off) = (0u64, 0u64);
+        'cls: for cls in self.cnf[v].iter() {
+            let mut care = 0;
+            let mut val = 0;
+            let mut vl = None;
+            for &l in cls.iter() {
+                if l.var() == v {
+                    vl = Some(l);
+                    continue;
+                }
+                if l.var().is_constant() {
+                    if l == Lit::constant(true) {
+                        continue 'cls;
+                    }
+                    continue;
+                }
+                let i = dep.iter().position(|&d| d == l.var())?;
+                care |= 1 << i;
+                if !l.polarity() {
+                    val |= 1 << i;
+                }
+            }
+            let vl = vl?;
+            for a in 0..1usize << k {
+                if a & care == val {
+                    if vl.polarity() {
+                        on |= 1 << a;
+                    } else {
+                        off |= 1 << a;
+                    }
+                }
+            }
+        }
+        (on & off == 0 && on | off == tt_full(k)).then_some(on)
+    }
+
+    fn structural_gate(&self, v: Var) -> Option<Gate> {
+        let rel = &self.cnf[v];
+        let long = rel.iter().position(|cls| cls.len() > 2)?;
+        let out = rel[long].last();
+        if rel.len() != rel[long].len() {
+            return None;
+        }
+        let mut ins = LitVec::new();
+        for (i, cls) in rel.iter().enumerate() {
+            if i == long {
+                continue;
+            }
+            if cls.len() != 2 || cls.last() != !out {
+                return None;
+            }
+            ins.push(cls[0]);
+        }
+        ins.sort();
+        ins.dedup();
+        let mut lins: LitVec = rel[long][..rel[long].len() - 1]
+            .iter()
+            .map(|l| !l)
+            .collect();
+        lins.sort();
+        if ins != lins {
+            return None;
+        }
+        if out.polarity() {
+            Some(Gate::And(ins))
+        } else {
+            Some(Gate::Or(!ins))
+        }
+    }
+
+    fn tt_gate(&self, f: u64, dep: &[Var]) -> Gate {
+        let k = dep.len();
+        let full = tt_full(k);
+        let m: Vec<u64> = (0..k).map(|i| tt_var(i, k)).collect();
+        let lit = |i: usize, p: bool| dep[i].lit().not_if(!p);
+        let mlit = |i: usize, p: bool| if p { m[i] } else { !m[i] & full };
+        if f == 0 || f == full {
+            return Gate::Equiv(Lit::constant(f == full));
+        }
+        if k == 1 {
+            return Gate::Equiv(lit(0, f == m[0]));
+        }
+        if f.count_ones() == 1 {
+            let a = f.trailing_zeros() as usize;
+            let mut ands: LitVec = (0..k).map(|i| lit(i, a >> i & 1 == 1)).collect();
+            ands.sort();
+            return Gate::And(ands);
+        }
+        let nf = !f & full;
+        if nf.count_ones() == 1 {
+            let a = nf.trailing_zeros() as usize;
+            let mut ors: LitVec = (0..k).map(|i| lit(i, a >> i & 1 == 0)).collect();
+            ors.sort();
+            return Gate::Or(ors);
+        }
+        if k == 2 {
+            let x = m[0] ^ m[1];
+            if f == x {
+                return Gate::Xor(lit(0, true), lit(1, true));
+            } else if f == !x & full {
+                return Gate::Xnor(lit(0, true), lit(1, true));
+            }
+        }
+        if k == 3 {
+            for (c, mc) in m.iter().enumerate() {
+                let (t, e) = ((c + 1) % 3, (c + 2) % 3);
+                for (t, e) in [(t, e), (e, t)] {
+                    for pt in [true, false] {
+                        for pe in [true, false] {
+                            if f == (mc & mlit(t, pt)) | (!mc & mlit(e, pe)) {
+                                return Gate::Ite(lit(c, true), lit(t, pt), lit(e, pe));
+                            }
+                        }
+                    }
+                }
+            }
+            for p in 0..8 {
+                let (x, y, z) = (
+                    mlit(0, p & 1 == 0),
+                    mlit(1, p & 2 == 0),
+                    mlit(2, p & 4 == 0),
+                );
+                if f == (x & y) | (x & z) | (y & z) {
+                    let mut maj = [lit(0, p & 1 == 0), lit(1, p & 2 == 0), lit(2, p & 4 == 0)];
+                    maj.sort();
+                    return Gate::Majority(maj[0], maj[1], maj[2]);
+                }
+            }
+        }
+        Gate::Unknown
+    }
+
+    /// Classify the relation of `v`. Relations with a support of at most
+    /// `GATE_TT_MAX_SUPPORT` vars are recognised semantically, larger ones
+    /// only in the shape produced by `LitVvec::cnf_and` and `LitVvec::cnf_or`.
+    /// Returns None for leaves and the constant var.
+    pub fn gate(&self, v: Var) -> Option<Gate> {
+        if v.is_constant() || self.is_leaf(v) {
+            return None;
+        }
+        if let Some(g) = self.structural_gate(v) {
+            return Some(g);
+        }
+        let mut dep: Vec<Var> = self.dep[v]
+            .iter()
+            .copied()
+            .filter(|d| !d.is_constant())
+            .collect();
+        if dep.len() > GATE_TT_MAX_SUPPORT {
+            return Some(Gate::Unknown);
+        }
+        dep.sort();
+        Some(match self.truth_table(v, &dep) {
+            Some(f) => self.tt_gate(f, &dep),
+            None => Gate::Unknown,
+        })
+    }
+
+    pub fn gates(&self) -> VarMap<Option<Gate>> {
+        let mut gates = VarMap::new_with(self.max_var);
+        for v in self.var_iter_woc() {
+            gates[v] = self.gate(v);
+        }
+        gates
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Gate;
+    use crate::{DagCnf, Lit, LitVec, LitVvec, Var};
+
+    #[test]
+    fn test_constructors() {
+        let mut dc = DagCnf::new();
+        dc.new_var_to(Var(3));
+        let (a, b, c) = (Lit::from(1), Lit::from(-2), Lit::from(3));
+        let and = dc.new_and([a, b, c]);
+        assert_eq!(dc.gate(and.var()), Some(Gate::And(LitVec::from([a, b, c]))));
+        let or = dc.new_or([a, b]);
+        assert_eq!(dc.gate(or.var()), Some(Gate::Or(LitVec::from([a, b]))));
+        let xor = dc.new_xor(a, b);
+        assert_eq!(dc.gate(xor.var()), Some(Gate::Xnor(a, !b)));
+        let ite = dc.new_ite(!a, b, c);
+        assert_eq!(dc.gate(ite.var()), Some(Gate::Ite(a, c, b)));
+        let n = dc.new_var();
+        dc.add_rel(n, &LitVvec::cnf_assign(!n.lit(), c));
+        assert_eq!(dc.gate(n), Some(Gate::Equiv(!c)));
+        assert_eq!(dc.gate(Var(1)), None);
+    }
+
+    #[test]
+    fn test_semantic() {
+        let mut dc = DagCnf::new();
+        dc.new_var_to(Var(3));
+        let (a, b, c) = (Lit::from(1), Lit::from(2), Lit::from(3));
+        let n = dc.new_var();
+        let m = n.lit();
+        dc.add_rel(
+            n,
+            &[
+                LitVec::from([!a, !b, m]),
+                LitVec::from([!a, !c, m]),
+                LitVec::from([!b, !c, m]),
+                LitVec::from([a, b, !m]),
+                LitVec::from([a, c, !m]),
+                LitVec::from([b, c, !m]),
+            ],
+        );
+        assert_eq!(dc.gate(n), Some(Gate::Majority(a, b, c)));
+        let n = dc.new_var();
+        let m = n.lit();
+        dc.add_rel(
+            n,
+            &[
+                LitVec::from([a, !m]),
+                LitVec::from([!b, !m]),
+                LitVec::from([!a, b, m]),
+            ],
+        );
+        assert_eq!(dc.gate(n), Some(Gate::And(LitVec::from([a, !b]))));
+        let n = dc.new_var();
+        dc.add_rel(n, &[LitVec::from([a, !n.lit()])]);
+        assert_eq!(dc.gate(n), Some(Gate::Unknown));
+        let n = dc.new_var();
+        let m = n.lit();
+        dc.add_rel(
+            n,
+            &[
+                LitVec::from([Lit::constant(false), !b, m]),
+                LitVec::from([b, !m]),
+            ],
+        );
+        assert_eq!(dc.gate(n), Some(Gate::Equiv(b)));
+    }
+}
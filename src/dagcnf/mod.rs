@@ -1,6 +1,10 @@
+mod cone;
+pub mod gate;
 pub mod simplify;
 pub mod simulate;
+pub mod ternary;
 mod top;
+pub mod validate;
 
 use crate::{Lit, LitVec, LitVvec, Var, VarLMap, VarMap, VarRange, VarVMap};
 use giputils::hash::GHashSet;
@@ -376,23 +380,30 @@ impl DagCnf {
     }
 
     pub fn migrate(&mut self, other: &DagCnf, t: Var, map: &mut VarVMap) {
-        if map.get(&t).is_some() {
-            return;
-        }
-        for rel in other[t].iter() {
-            for &l in rel.iter() {
-                if l.var() != t {
-                    self.migrate(other, l.var(), map);
+        let mut stack = vec![(t, false)];
+        while let Some((v, visited)) = stack.pop() {
+            if map.get(&v).is_some() {
+                continue;
+            }
+            if !visited {
+                stack.push((v, true));
+                for rel in other[v].iter().rev() {
+                    for &l in rel.iter().rev() {
+                        if l.var() != v && map.get(&l.var()).is_none() {
+                            stack.push((l.var(), false));
+                        }
+                    }
                 }
+                continue;
             }
+            let n = self.new_var();
+            map.insert(v, n);
+            let mut new_rel = Vec::new();
+            for rel in other[v].iter() {
+                new_rel.push(rel.map(|l| map.lit_map(l).unwrap()));
+            }
+            self.add_rel(n, &new_rel);
         }
-        let n = self.new_var();
-        map.insert(t, n);
-        let mut new_rel = Vec::new();
-        for rel in other[t].iter() {
-            new_rel.push(rel.map(|l| map.lit_map(l).unwrap()));
-        }
-        self.add_rel(n, &new_rel);
     }
 }
 
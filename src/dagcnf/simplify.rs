@@ -1,7 +1,7 @@
 use super::DagCnf;
 use crate::{
-    LitMap, LitOrdVec, LitVec, LitVvec, Var, VarAssign, VarRange, lemmas_subsume_simplify,
-    occur::Occurs,
+    Lit, LitMap, LitOrdVec, LitVec, LitVvec, ReconstructStack, Var, VarAssign, VarLMap, VarRange,
+    lemmas_subsume_simplify, occur::Occurs,
 };
 use giputils::{allocator::Gallocator, hash::GHashSet, heap::BinaryHeap, ptr::Grc};
 use log::debug;
@@ -10,6 +10,65 @@ use std::{
     time::{Duration, Instant},
 };
 
+#[derive(Clone, Debug)]
+pub struct DagCnfSimplifyConfig {
+    /// Time budget of all phases together. Constant propagation stops early
+    /// once it is spent, the fixed vars still used by the relations it did
+    /// not reach are then kept.
+    pub time_limit: Option<Duration>,
+    /// Number of clauses BVE may add over the number it removes.
+    pub max_clause_growth: usize,
+    pub max_resolvent_len: usize,
+    /// Vars with more clauses than this are not eliminated.
+    pub max_occur: usize,
+    /// Propagation budget of failed-literal probing, counted in clause visits.
+    pub probe_budget: usize,
+    /// Propagation budget of vivification, counted in clause visits.
+    pub vivify_budget: usize,
+    pub probe: bool,
+    pub const_simp: bool,
+    pub equiv: bool,
+    pub vivify: bool,
+    pub bve: bool,
+    pub subsume: bool,
+}
+
+impl Default for DagCnfSimplifyConfig {
+    fn default() -> Self {
+        Self {
+            time_limit: None,
+            max_clause_growth: 0,
+            max_resolvent_len: usize::MAX,
+            max_occur: 2000,
+            probe_budget: 1_000_000,
+            vivify_budget: 1_000_000,
+            probe: true,
+            const_simp: true,
+            equiv: true,
+            vivify: true,
+            bve: true,
+            subsume: true,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct DagCnfSimplifyStats {
+    pub num_eliminated: usize,
+    pub num_substituted: usize,
+    pub num_failed_lits: usize,
+    pub num_probe_equivs: usize,
+    pub num_removed_cls: usize,
+    pub num_added_cls: usize,
+    pub num_strengthened_cls: usize,
+    pub const_time: Duration,
+    pub equiv_time: Duration,
+    pub probe_time: Duration,
+    pub vivify_time: Duration,
+    pub bve_time: Duration,
+    pub subsume_time: Duration,
+}
+
 pub struct DagCnfSimplify {
     cdb: Grc<Gallocator<LitOrdVec>>,
     max_var: Var,
@@ -18,8 +77,14 @@ pub struct DagCnfSimplify {
     occur: Option<(Grc<Occurs<LitOrdVec>>, BinaryHeap<Var, Occurs<LitOrdVec>>)>,
     frozen: GHashSet<Var>,
     value: VarAssign,
+    rstack: ReconstructStack,
+    emap: VarLMap,
+    /// Equivalent literals found by probing, substituted by `equiv_simplify`.
+    probe_equivs: Vec<(Lit, Lit)>,
     num_ocls: usize,
     time: Duration,
+    config: DagCnfSimplifyConfig,
+    stats: DagCnfSimplifyStats,
 }
 
 impl DagCnfSimplify {
@@ -36,8 +101,13 @@ impl DagCnfSimplify {
             cnf,
             frozen: GHashSet::from_iter([Var::CONST]),
             value,
+            rstack: ReconstructStack::new(),
+            emap: VarLMap::new(),
+            probe_equivs: Vec::new(),
             num_ocls,
             time: Duration::default(),
+            config: DagCnfSimplifyConfig::default(),
+            stats: DagCnfSimplifyStats::default(),
         };
         for v in VarRange::new_inclusive(Var::CONST, max_var) {
             for mut cls in dagcnf.cnf[v].clone() {
@@ -47,6 +117,7 @@ impl DagCnfSimplify {
                 res.add_rel(cls);
             }
         }
+        res.stats.num_added_cls = 0;
         res
     }
 
@@ -81,6 +152,23 @@ impl DagCnfSimplify {
         self.frozen.insert(v);
     }
 
+    #[inline]
+    pub fn set_config(&mut self, config: DagCnfSimplifyConfig) {
+        self.config = config;
+    }
+
+    #[inline]
+    pub fn stats(&self) -> &DagCnfSimplifyStats {
+        &self.stats
+    }
+
+    #[inline]
+    fn out_of_time(&self, start: Instant) -> bool {
+        self.config
+            .time_limit
+            .is_some_and(|l| self.time + start.elapsed() > l)
+    }
+
     fn add_rel(&mut self, rel: LitVec) {
         let Some(rel) = rel.ordered_simp(&self.value) else {
             return;
@@ -92,6 +180,7 @@ impl DagCnfSimplify {
             self.value.set(n);
         }
         let relid = self.cdb.alloc(rel);
+        self.stats.num_added_cls += 1;
         self.cnf[n].push(relid);
         if let Some((occur, qbve)) = &mut self.occur {
             for &l in self.cdb[relid].iter() {
@@ -145,6 +234,7 @@ impl DagCnfSimplify {
                         }
                     }
                     self.cdb.dealloc(cls);
+                    self.stats.num_removed_cls += 1;
                 } else {
                     i += 1;
                 }
@@ -169,6 +259,7 @@ impl DagCnfSimplify {
                 }
             }
             self.cdb.dealloc(cls);
+            self.stats.num_removed_cls += 1;
         }
         self.cnf[ln].clear();
         self.cnf[!ln].clear();
@@ -193,6 +284,9 @@ impl DagCnfSimplify {
         for &pcls in pcnf {
             for &ncls in ncnf {
                 if let Some(resolvent) = self.cdb[pcls].ordered_resolvent(&self.cdb[ncls], pivot) {
+                    if resolvent.len() > self.config.max_resolvent_len {
+                        return None;
+                    }
                     res.push(resolvent);
                 }
                 if res.len() > limit {
@@ -211,9 +305,10 @@ impl DagCnfSimplify {
         let occur = &mut self.occur.as_mut().unwrap().0;
         let ocost =
             occur.num_occur(lv) + occur.num_occur(!lv) + self.cnf[lv].len() + self.cnf[!lv].len();
-        if ocost == 0 || ocost > 2000 {
+        if ocost == 0 || ocost > self.config.max_occur {
             return;
         }
+        let ocost = ocost + self.config.max_clause_growth;
         let (pos, neg) = (self.cnf[lv].clone(), self.cnf[!lv].clone());
         let mut ncost = 0;
         let mut opos = occur.get(lv).to_vec();
@@ -236,19 +331,31 @@ impl DagCnfSimplify {
         res.extend(resnp);
         let res = clause_subsume_simplify(res);
         opos.extend(oneg);
+        let ecls = opos
+            .iter()
+            .chain(pos.iter())
+            .chain(neg.iter())
+            .map(|&c| self.cdb[c].as_litvec().clone())
+            .collect();
+        self.rstack.push_eliminate(v, ecls);
         self.remove_rels(opos);
         self.remove_node(v);
         for r in res {
             self.add_rel(r);
         }
+        self.stats.num_eliminated += 1;
     }
 
     pub fn bve_simplify(&mut self) {
         let start = Instant::now();
         self.enable_occur();
         while let Some(v) = self.occur.as_mut().unwrap().1.pop() {
+            if self.out_of_time(start) {
+                break;
+            }
             self.eliminate(v);
         }
+        self.stats.bve_time += start.elapsed();
         self.time += start.elapsed();
     }
 
@@ -278,12 +385,14 @@ impl DagCnfSimplify {
             if res {
                 self.cnf[self.cdb[cj].last()].retain(|&c| c != cj);
                 self.cdb.dealloc(cj);
+                self.stats.num_removed_cls += 1;
                 continue;
             } else if let Some(diff) = diff {
                 if self.cdb[ci].len() == self.cdb[cj].len() {
                     if diff.var() == self.cdb[ci].last().var() {
                         self.cdb.dealloc(ci);
                         self.cdb.dealloc(cj);
+                        self.stats.num_removed_cls += 2;
                         self.cnf[self.cdb[ci].last()].retain(|&c| c != ci);
                         self.cnf[self.cdb[cj].last()].retain(|&c| c != cj);
                         return;
@@ -293,14 +402,18 @@ impl DagCnfSimplify {
                     self.cdb[ci] = LitOrdVec::new(cube);
                     self.cnf[self.cdb[cj].last()].retain(|&c| c != cj);
                     self.cdb.dealloc(cj);
+                    self.stats.num_strengthened_cls += 1;
+                    self.stats.num_removed_cls += 1;
                 } else if diff.var() == self.cdb[cj].last().var() {
                     self.cnf[self.cdb[cj].last()].retain(|&c| c != cj);
                     self.cdb.dealloc(cj);
+                    self.stats.num_removed_cls += 1;
                 } else {
                     let mut cube = self.cdb[cj].as_litvec().clone();
                     assert!(cube.last() == self.cdb[cj].last());
                     cube.retain(|l| *l != !diff);
                     self.cdb[cj] = LitOrdVec::new(cube);
+                    self.stats.num_strengthened_cls += 1;
                 }
             }
         }
@@ -310,6 +423,9 @@ impl DagCnfSimplify {
         let start = Instant::now();
         self.enable_occur();
         for v in VarRange::new_inclusive(Var::CONST, self.max_var) {
+            if self.out_of_time(start) {
+                break;
+            }
             for cls in self.cnf[v.lit()].clone() {
                 self.cls_subsume_check(cls);
             }
@@ -322,6 +438,7 @@ impl DagCnfSimplify {
             self.cnf[v.lit()].retain(|&c| !self.cdb.is_removed(c));
             self.cnf[!v.lit()].retain(|&c| !self.cdb.is_removed(c));
         }
+        self.stats.subsume_time += start.elapsed();
         self.time += start.elapsed();
     }
 
@@ -334,6 +451,7 @@ impl DagCnfSimplify {
                 if scls.last().var() != v {
                     removed.push(c);
                 } else if cls.len() != scls.len() {
+                    self.stats.num_strengthened_cls += 1;
                     self.add_rel(scls);
                 }
             } else {
@@ -346,20 +464,349 @@ impl DagCnfSimplify {
     pub fn const_simplify(&mut self) {
         let start = Instant::now();
         self.disable_occur();
+        let mut used = GHashSet::new();
         for v in VarRange::new_inclusive(Var(1), self.max_var) {
+            if self.out_of_time(start) {
+                for u in VarRange::new_inclusive(v, self.max_var) {
+                    for c in self.var_rels(u) {
+                        used.extend(self.cdb[c].iter().map(|l| l.var()).filter(|&d| d != u));
+                    }
+                }
+                break;
+            }
             self.const_simp_var(v);
         }
         for v in VarRange::new_inclusive(Var(1), self.max_var) {
             let ln = v.lit();
             let vv = self.value.v(ln);
-            if !vv.is_none() {
+            if !vv.is_none() && !used.contains(&v) {
+                self.rstack.push_assign(ln.not_if(vv.is_false()));
                 self.remove_node(v);
                 if self.frozen.contains(&v) {
                     self.add_rel(LitVec::from(ln.not_if(vv.is_false())));
                 }
             }
         }
+        self.stats.const_time += start.elapsed();
+        self.time += start.elapsed();
+    }
+
+    fn prop_occurs(&self) -> LitMap<Vec<usize>> {
+        let mut occ: LitMap<Vec<usize>> = LitMap::new_with(self.max_var);
+        for v in VarRange::new_inclusive(Var::CONST, self.max_var) {
+            for &c in self.cnf[v.lit()].iter().chain(self.cnf[!v.lit()].iter()) {
+                for &l in self.cdb[c].iter() {
+                    occ[l].push(c);
+                }
+            }
+        }
+        occ
+    }
+
+    /// Assign `lit` on top of `self.value` and propagate over the clauses of
+    /// `occ` except `skip`, pushing the assigned literals to `trail`. Returns
+    /// false on conflict. Propagation stops early once `budget` runs out.
+    fn propagate(
+        &mut self,
+        occ: &LitMap<Vec<usize>>,
+        lit: Lit,
+        skip: Option<usize>,
+        trail: &mut Vec<Lit>,
+        budget: &mut usize,
+    ) -> bool {
+        let lv = self.value.v(lit);
+        if !lv.is_none() {
+            return lv.is_true();
+        }
+        let mut head = trail.len();
+        self.value.set(lit);
+        trail.push(lit);
+        while head < trail.len() {
+            let l = trail[head];
+            head += 1;
+            for &c in occ[!l].iter() {
+                if *budget == 0 {
+                    return true;
+                }
+                *budget -= 1;
+                if Some(c) == skip || self.cdb.is_removed(c) {
+                    continue;
+                }
+                let mut unit = None;
+                let mut num_none = 0;
+                let mut sat = false;
+                for &x in self.cdb[c].iter() {
+                    let xv = self.value.v(x);
+                    if xv.is_true() {
+                        sat = true;
+                        break;
+                    } else if xv.is_none() {
+                        num_none += 1;
+                        unit = Some(x);
+                    }
+                }
+                if sat {
+                    continue;
+                }
+                match (num_none, unit) {
+                    (0, _) => return false,
+                    (1, Some(u)) => {
+                        self.value.set(u);
+                        trail.push(u);
+                    }
+                    _ => (),
+                }
+            }
+        }
+        true
+    }
+
+    #[inline]
+    fn backtrack(&mut self, trail: &mut Vec<Lit>, len: usize) {
+        for l in trail.drain(len..) {
+            self.value.set_none(l.var());
+        }
+    }
+
+    /// Fix `lit` at the root level. Returns false if the formula is found
+    /// unsatisfiable, in which case nothing is fixed.
+    fn probe_fix(
+        &mut self,
+        occ: &LitMap<Vec<usize>>,
+        lit: Lit,
+        trail: &mut Vec<Lit>,
+        budget: &mut usize,
+    ) -> bool {
+        if !self.propagate(occ, lit, None, trail, budget) {
+            self.backtrack(trail, 0);
+            return false;
+        }
+        trail.clear();
+        true
+    }
+
+    /// Failed-literal probing at the root level. Each unassigned var is
+    /// propagated in both polarities: a conflicting polarity fixes the other
+    /// one, literals implied by both are fixed, and literals implied equal to
+    /// the var are recorded for `equiv_simplify` to substitute. Fixed literals
+    /// land in `value`, to be applied by `const_simplify`.
+    pub fn probe_simplify(&mut self) {
+        let start = Instant::now();
+        self.disable_occur();
+        let occ = self.prop_occurs();
+        let mut budget = self.config.probe_budget;
+        let mut trail = Vec::new();
+        let mut equivs = Vec::new();
+        'probe: for v in VarRange::new_inclusive(Var(1), self.max_var) {
+            if budget == 0 || self.out_of_time(start) {
+                break;
+            }
+            if !self.value.v(v.lit()).is_none() {
+                continue;
+            }
+            let mut imp = [GHashSet::new(), GHashSet::new()];
+            for (i, l) in [v.lit(), !v.lit()].into_iter().enumerate() {
+                let res = self.propagate(&occ, l, None, &mut trail, &mut budget);
+                imp[i].extend(trail.iter().skip(1).copied());
+                self.backtrack(&mut trail, 0);
+                if !res {
+                    self.stats.num_failed_lits += 1;
+                    if !self.probe_fix(&occ, !l, &mut trail, &mut budget) {
+                        break 'probe;
+                    }
+                    continue 'probe;
+                }
+            }
+            let mut fixed = Vec::new();
+            for &l in imp[0].iter() {
+                if imp[1].contains(&l) {
+                    fixed.push(l);
+                } else if imp[1].contains(&!l) {
+                    equivs.push((v.lit(), l));
+                }
+            }
+            for l in fixed {
+                if !self.probe_fix(&occ, l, &mut trail, &mut budget) {
+                    break 'probe;
+                }
+            }
+        }
+        for (x, y) in equivs {
+            if self.value.v(x).is_none() && self.value.v(y).is_none() {
+                self.stats.num_probe_equivs += 1;
+                self.probe_equivs.push((x, y));
+            }
+        }
+        self.stats.probe_time += start.elapsed();
+        self.time += start.elapsed();
+    }
+
+    /// Shorten relation clauses by propagating over the other clauses of the
+    /// same relation, so that the relation keeps defining the same function.
+    /// The literal of the var is assigned false, then the negations of the
+    /// other literals one by one: literals implied false are dropped, and the
+    /// clause is cut at the first literal implied true or leading to a
+    /// conflict. The literal of the var is always kept.
+    pub fn vivify_simplify(&mut self) {
+        let start = Instant::now();
+        self.disable_occur();
+        let mut occ: LitMap<Vec<usize>> = LitMap::new_with(self.max_var);
+        let mut budget = self.config.vivify_budget;
+        let mut trail = Vec::new();
+        let mut res = Vec::new();
+        for v in VarRange::new_inclusive(Var(1), self.max_var) {
+            if budget == 0 || self.out_of_time(start) {
+                break;
+            }
+            if !self.value.v(v.lit()).is_none() {
+                continue;
+            }
+            let rels = self.var_rels(v);
+            for &c in rels.iter() {
+                for &l in self.cdb[c].iter() {
+                    occ[l].push(c);
+                }
+            }
+            for &c in rels.iter() {
+                if self.cdb[c].len() < 2 {
+                    continue;
+                }
+                let cls = self.cdb[c].as_litvec().clone();
+                let n = cls.last();
+                let mut ncls = LitVec::new();
+                let mut cut = !self.propagate(&occ, !n, Some(c), &mut trail, &mut budget);
+                for &l in cls.iter().take(cls.len() - 1) {
+                    if cut {
+                        break;
+                    }
+                    let lv = self.value.v(l);
+                    if lv.is_false() {
+                        continue;
+                    }
+                    ncls.push(l);
+                    cut =
+                        lv.is_true() || !self.propagate(&occ, !l, Some(c), &mut trail, &mut budget);
+                }
+                self.backtrack(&mut trail, 0);
+                ncls.push(n);
+                if ncls.len() < cls.len() {
+                    res.push((c, ncls));
+                }
+            }
+            for &c in rels.iter() {
+                for &l in self.cdb[c].iter() {
+                    occ[l].clear();
+                }
+            }
+        }
+        self.remove_rels(res.iter().map(|(c, _)| *c).collect());
+        for (_, cls) in res {
+            self.stats.num_strengthened_cls += 1;
+            self.add_rel(cls);
+        }
+        self.stats.vivify_time += start.elapsed();
+        self.time += start.elapsed();
+    }
+
+    /// Equivalence classes of literals in the binary implication graph and
+    /// the equivalences found by probing, the representative literal has the
+    /// lowest var of its class.
+    fn equiv_classes(&self) -> VarLMap {
+        let mut graph: LitMap<Vec<Lit>> = LitMap::new_with(self.max_var);
+        for &(x, y) in self.probe_equivs.iter() {
+            for (x, y) in [(x, y), (!x, !y)] {
+                graph[x].push(y);
+                graph[y].push(x);
+            }
+        }
+        for v in VarRange::new_inclusive(Var::CONST, self.max_var) {
+            for &c in self.cnf[v.lit()].iter().chain(self.cnf[!v.lit()].iter()) {
+                let cls = &self.cdb[c];
+                if cls.len() == 2 {
+                    graph[!cls[0]].push(cls[1]);
+                    graph[!cls[1]].push(cls[0]);
+                }
+            }
+        }
+        let mut map = VarLMap::new();
+        for scc in lit_scc(&graph, self.max_var) {
+            if scc.len() < 2 {
+                continue;
+            }
+            let mut vars: Vec<Var> = scc.iter().map(|l| l.var()).collect();
+            vars.sort();
+            vars.dedup();
+            if vars.len() != scc.len() || vars.iter().any(|&v| !self.value.v(v.lit()).is_none()) {
+                continue;
+            }
+            let rep = *scc.iter().min_by_key(|l| l.var()).unwrap();
+            for &l in scc.iter() {
+                if l != rep {
+                    map.insert_lit(l, rep);
+                }
+            }
+        }
+        map
+    }
+
+    /// Substitute each class of equivalent literals by its representative.
+    /// The relation of a substituted var is dropped, as the relations are
+    /// total it holds whatever the leaves once the var equals its
+    /// representative. Frozen vars stay defined as equal to their
+    /// representative. Returns the substitutions of this pass, in the form
+    /// taken by `DagCnf::replace`.
+    pub fn equiv_simplify(&mut self) -> VarLMap {
+        let start = Instant::now();
+        self.disable_occur();
+        let map = self.equiv_classes();
+        self.probe_equivs.clear();
+        if !map.is_empty() {
+            let mut removed = Vec::new();
+            let mut added = Vec::new();
+            for v in VarRange::new_inclusive(Var::CONST, self.max_var) {
+                for &c in self.cnf[v.lit()].iter().chain(self.cnf[!v.lit()].iter()) {
+                    if map.contains_key(&v) {
+                        removed.push(c);
+                    } else if self.cdb[c].iter().any(|l| map.contains_key(&l.var())) {
+                        removed.push(c);
+                        let mut cls = self.cdb[c].map(|l| map.map_lit(l).unwrap_or(l));
+                        cls.sort();
+                        added.push(cls);
+                    }
+                }
+            }
+            self.remove_rels(removed);
+            for cls in added {
+                self.add_rel(cls);
+            }
+            let mut vars: Vec<_> = map.iter().map(|(&v, &l)| (v, l)).collect();
+            vars.sort();
+            for (v, l) in vars {
+                if self.frozen.contains(&v) {
+                    for cls in LitVvec::cnf_assign(v.lit(), l) {
+                        self.add_rel(cls);
+                    }
+                } else {
+                    self.rstack.push_equiv(v, l);
+                }
+            }
+            for t in self.emap.values_mut() {
+                if let Some(n) = map.map_lit(*t) {
+                    *t = n;
+                }
+            }
+            self.emap.extend(map.iter().map(|(&v, &l)| (v, l)));
+            self.stats.num_substituted += map.len();
+        }
+        self.stats.equiv_time += start.elapsed();
         self.time += start.elapsed();
+        map
+    }
+
+    /// All substitutions done by `equiv_simplify` so far.
+    #[inline]
+    pub fn equiv_map(&self) -> &VarLMap {
+        &self.emap
     }
 
     pub fn finalize(&mut self) -> DagCnf {
@@ -385,22 +832,123 @@ impl DagCnfSimplify {
         }
         self.time += start.elapsed();
         debug!(
-            "dagcnf simplified from {} to {} clauses in {:.2}s",
+            "dagcnf simplified from {} to {} clauses in {:.2}s, {} vars eliminated",
             self.num_ocls,
             dagcnf.num_clause(),
-            self.time.as_secs_f64()
+            self.time.as_secs_f64(),
+            self.stats.num_eliminated
         );
         dagcnf
     }
 
+    /// Extend `assign`, a model of the simplified `DagCnf`, to the vars fixed
+    /// or eliminated during simplification. It is enough for `assign` to give
+    /// the leaves of the simplified `DagCnf`, its gates are evaluated from
+    /// them first.
+    pub fn reconstruct(&self, assign: &VarAssign) -> VarAssign {
+        let mut assign = assign.clone();
+        assign.reserve(self.max_var);
+        for v in VarRange::new_inclusive(Var(1), self.max_var) {
+            if !assign.v(v.lit()).is_none() {
+                continue;
+            }
+            for c in self.var_rels(v) {
+                let cls = &self.cdb[c];
+                if cls.iter().all(|&l| l.var() == v || assign.v(l).is_false()) {
+                    assign.set(cls.last());
+                    break;
+                }
+            }
+        }
+        self.rstack.extend(&mut assign);
+        assign
+    }
+
+    #[inline]
+    pub fn reconstruct_stack(&self) -> &ReconstructStack {
+        &self.rstack
+    }
+
     pub fn simplify(&mut self) -> DagCnf {
-        self.const_simplify();
-        self.bve_simplify();
-        self.subsume_simplify();
+        if self.config.probe {
+            self.probe_simplify();
+        }
+        if self.config.const_simp {
+            self.const_simplify();
+        }
+        if self.config.equiv {
+            self.equiv_simplify();
+        }
+        if self.config.vivify {
+            self.vivify_simplify();
+        }
+        if self.config.bve {
+            self.bve_simplify();
+        }
+        if self.config.subsume {
+            self.subsume_simplify();
+        }
         self.finalize()
     }
 }
 
+/// Strongly connected components of the literal graph, by iterative Tarjan.
+fn lit_scc(graph: &LitMap<Vec<Lit>>, max_var: Var) -> Vec<Vec<Lit>> {
+    let mut index: LitMap<usize> = LitMap::new_with(max_var);
+    let mut low: LitMap<usize> = LitMap::new_with(max_var);
+    let mut on_stack: LitMap<bool> = LitMap::new_with(max_var);
+    let mut stack = Vec::new();
+    let mut sccs = Vec::new();
+    let mut next = 1;
+    for v in VarRange::new_inclusive(Var::CONST, max_var) {
+        for root in [v.lit(), !v.lit()] {
+            if index[root] != 0 {
+                continue;
+            }
+            index[root] = next;
+            low[root] = next;
+            next += 1;
+            stack.push(root);
+            on_stack[root] = true;
+            let mut call = vec![(root, 0)];
+            while let Some((l, i)) = call.last_mut() {
+                let l = *l;
+                if let Some(&w) = graph[l].get(*i) {
+                    *i += 1;
+                    if index[w] == 0 {
+                        index[w] = next;
+                        low[w] = next;
+                        next += 1;
+                        stack.push(w);
+                        on_stack[w] = true;
+                        call.push((w, 0));
+                    } else if on_stack[w] {
+                        low[l] = low[l].min(index[w]);
+                    }
+                    continue;
+                }
+                call.pop();
+                if let Some(&(p, _)) = call.last() {
+                    low[p] = low[p].min(low[l]);
+                }
+                if low[l] == index[l] {
+                    let mut scc = Vec::new();
+                    loop {
+                        let w = stack.pop().unwrap();
+                        on_stack[w] = false;
+                        scc.push(w);
+                        if w == l {
+                            break;
+                        }
+                    }
+                    sccs.push(scc);
+                }
+            }
+        }
+    }
+    sccs
+}
+
 fn clause_subsume_simplify(lemmas: LitVvec) -> LitVvec {
     let lemmas: Vec<LitOrdVec> = lemmas.into_iter().map(LitOrdVec::new).collect();
     let lemmas = lemmas_subsume_simplify(lemmas);
@@ -418,11 +966,30 @@ impl DagCnf {
         }
         simp.simplify()
     }
+
+    pub fn simplify_with_config(
+        &self,
+        frozen: impl IntoIterator<Item = impl Into<Var>>,
+        config: DagCnfSimplifyConfig,
+    ) -> (Self, DagCnfSimplifyStats) {
+        let mut simp = DagCnfSimplify::new(self);
+        simp.set_config(config);
+        for v in frozen.into_iter().map(|l| l.into()).chain(once(Var::CONST)) {
+            simp.froze(v);
+        }
+        let res = simp.simplify();
+        (res, simp.stats)
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::{DagCnf, Lit, Var, VarRange, simplify::DagCnfSimplify};
+    use crate::{
+        DagCnf, Lit, LitVec, LitVvec, Var, VarAssign, VarRange,
+        gate::Gate,
+        simplify::{DagCnfSimplify, DagCnfSimplifyConfig},
+    };
+    use std::time::Duration;
 
     #[test]
     fn test0() {
@@ -438,4 +1005,161 @@ mod test {
         let ndc = simp.simplify();
         println!("{ndc}");
     }
+
+    #[test]
+    fn test_reconstruct() {
+        let mut dc = DagCnf::new();
+        dc.new_var_to(Var(3));
+        let x = dc.new_and([Lit::from(1), Lit::from(2)]);
+        let y = dc.new_and([x, Lit::from(3)]);
+        let mut simp = DagCnfSimplify::new(&dc);
+        for v in [Var(1), Var(2), Var(3), y.var()] {
+            simp.froze(v);
+        }
+        let ndc = simp.simplify();
+        assert!(ndc.is_leaf(x.var()));
+        let mut assign = VarAssign::new_with(ndc.max_var());
+        for l in [Lit::from(1), Lit::from(2), Lit::from(-3), !y] {
+            assign.set(l);
+        }
+        let assign = simp.reconstruct(&assign);
+        assert!(assign.v(x).is_true());
+        assert!(
+            dc.clause()
+                .all(|cls| cls.iter().any(|&l| assign.v(l).is_true()))
+        );
+
+        let mut assign = VarAssign::new_with(ndc.max_var());
+        for l in [Lit::from(1), Lit::from(2), Lit::from(3)] {
+            assign.set(l);
+        }
+        let assign = simp.reconstruct(&assign);
+        assert!(assign.v(x).is_true() && assign.v(y).is_true());
+        assert!(
+            dc.clause()
+                .all(|cls| cls.iter().any(|&l| assign.v(l).is_true()))
+        );
+    }
+
+    #[test]
+    fn test_const_timeout() {
+        let mut dc = DagCnf::new();
+        dc.new_var_to(Var(2));
+        let (a, w) = (Lit::from(1), Lit::from(2));
+        let v = dc.new_and([a, !a]);
+        let u = dc.new_or([v, w]);
+        let mut simp = DagCnfSimplify::new(&dc);
+        for x in [a.var(), w.var(), u.var()] {
+            simp.froze(x);
+        }
+        simp.probe_simplify();
+        simp.set_config(DagCnfSimplifyConfig {
+            time_limit: Some(Duration::ZERO),
+            ..Default::default()
+        });
+        simp.const_simplify();
+        let ndc = simp.finalize();
+        assert!(ndc.validate().is_ok());
+        for m in 0..4 {
+            let mut assign = VarAssign::new_with(dc.max_var());
+            assign.set(a.not_if(m & 1 == 0));
+            assign.set(w.not_if(m & 2 == 0));
+            let val = dc.ternary_simulation(&assign).val(u);
+            assert_eq!(ndc.ternary_simulation(&assign).val(u), val);
+        }
+    }
+
+    #[test]
+    fn test_config() {
+        let mut dc = DagCnf::new();
+        dc.new_var_to(Var(3));
+        let x = dc.new_and([Lit::from(1), Lit::from(2)]);
+        let y = dc.new_and([x, Lit::from(3)]);
+        let frozen = [Var(1), Var(2), Var(3), y.var()];
+        let config = DagCnfSimplifyConfig {
+            bve: false,
+            ..Default::default()
+        };
+        let (ndc, stats) = dc.simplify_with_config(frozen, config);
+        assert!(!ndc.is_leaf(x.var()));
+        assert_eq!(stats.num_eliminated, 0);
+        let (ndc, stats) = dc.simplify_with_config(frozen, DagCnfSimplifyConfig::default());
+        assert!(ndc.is_leaf(x.var()));
+        assert_eq!(stats.num_eliminated, 1);
+        let config = DagCnfSimplifyConfig {
+            probe: false,
+            const_simp: false,
+            equiv: false,
+            vivify: false,
+            bve: false,
+            subsume: false,
+            ..Default::default()
+        };
+        let (_, stats) = dc.simplify_with_config(frozen, config);
+        assert_eq!(stats.num_added_cls, 0);
+    }
+
+    #[test]
+    fn test_equiv() {
+        let mut dc = DagCnf::new();
+        dc.new_var_to(Var(2));
+        let (a, b) = (Lit::from(1), Lit::from(2));
+        let x = dc.new_and([a, b]);
+        let y = dc.new_var();
+        dc.add_rel(y, &LitVvec::cnf_assign(y.lit(), !x));
+        let y = y.lit();
+        let z = dc.new_and([!y, Lit::from(2)]);
+        let mut simp = DagCnfSimplify::new(&dc);
+        for v in [Var(1), Var(2), z.var()] {
+            simp.froze(v);
+        }
+        let map = simp.equiv_simplify();
+        assert_eq!(map.map(y.var()), Some(!x));
+        let ndc = simp.finalize();
+        assert!(ndc.is_leaf(y.var()));
+        assert!(ndc.validate().is_ok());
+        let mut odc = dc.clone();
+        odc.replace(&map);
+        assert!(odc.is_leaf(y.var()));
+    }
+
+    #[test]
+    fn test_probe_vivify() {
+        let mut dc = DagCnf::new();
+        dc.new_var_to(Var(2));
+        let (a, c) = (Lit::from(1), Lit::from(2));
+        let f = dc.new_and([c, !c]);
+        let x = dc.new_or([a, f]);
+        let z = dc.new_and([x, c]);
+        let mut simp = DagCnfSimplify::new(&dc);
+        for v in [a.var(), c.var(), z.var()] {
+            simp.froze(v);
+        }
+        simp.probe_simplify();
+        assert_eq!(simp.stats().num_probe_equivs, 1);
+        let map = simp.equiv_simplify();
+        assert_eq!(map.map_lit(x), Some(a));
+        simp.const_simplify();
+        let ndc = simp.finalize();
+        assert!(ndc.is_leaf(x.var()) && ndc.is_leaf(f.var()));
+        assert!(ndc.validate().is_ok());
+
+        let mut dc = DagCnf::new();
+        dc.new_var_to(Var(2));
+        let g1 = dc.new_and([a, c]);
+        let g2 = dc.new_and([a, !c]);
+        let y = dc.new_or([g1, g2]);
+        let w = dc.new_or([y, c]);
+        let mut simp = DagCnfSimplify::new(&dc);
+        for v in [a.var(), c.var(), w.var()] {
+            simp.froze(v);
+        }
+        simp.bve_simplify();
+        let n = simp.stats().num_strengthened_cls;
+        simp.vivify_simplify();
+        assert!(simp.stats().num_strengthened_cls > n);
+        let ndc = simp.finalize();
+        assert!(ndc.validate().is_ok());
+        assert_eq!(ndc.gate(w.var()), Some(Gate::Or(LitVec::from([a, c]))));
+    }
 }
@@ -1,15 +1,17 @@
 use super::DagCnf;
 use crate::Lit;
-use crate::{Var, VarMap, VarRange};
+use crate::{Var, VarAssign, VarBitVec, VarMap, VarRange, satif::Satif};
 use giputils::bitvec::BitVec;
 use giputils::hash::GHashSet;
-use rand::SeedableRng;
 use rand::rngs::StdRng;
-use std::ops::Index;
+use rand::{RngExt, SeedableRng};
+use std::{borrow::Borrow, ops::Index, thread};
 
 #[derive(Clone, Debug)]
 pub struct DagCnfSimulation {
     pub(crate) sim: VarMap<BitVec>,
+    /// Seed of the next random fill.
+    seed: u64,
 }
 
 impl Index<Var> for DagCnfSimulation {
@@ -31,27 +33,44 @@ impl DagCnfSimulation {
         }
     }
 
-    fn simulate_var(&mut self, v: Var, dc: &DagCnf) {
+    #[inline]
+    fn rng(&mut self) -> StdRng {
+        self.seed = self.seed.wrapping_add(1);
+        StdRng::seed_from_u64(self.seed)
+    }
+
+    #[inline]
+    pub fn num_bit(&self) -> usize {
+        self.sim[Var::CONST].len()
+    }
+
+    fn eval_var(&self, v: Var, dc: &DagCnf) -> BitVec {
+        let mut res = BitVec::from_elem(self.num_bit(), false);
         for rel in dc.cnf[v].iter() {
-            let mut sim = self.val(rel[0]);
+            let val = |l: Lit, res: &BitVec| {
+                let x = if l.var() == v {
+                    res
+                } else {
+                    &self.sim[l.var()]
+                };
+                if l.polarity() { x.clone() } else { !x }
+            };
+            let mut sim = val(rel[0], &res);
             let mut vl = rel[0];
             for &l in &rel[1..] {
                 if l.var() == v {
                     vl = l;
                 }
-                if l.polarity() {
-                    sim |= &self[l.var()];
-                } else {
-                    sim |= &!&self[l.var()];
-                }
+                sim |= &val(l, &res);
             }
             assert!(vl.var() == v);
             if vl.polarity() {
-                self.sim[v] |= &!&sim;
+                res |= &!&sim;
             } else {
-                self.sim[v] &= &sim;
+                res &= &sim;
             }
         }
+        res
     }
 
     pub fn simulate(&mut self, dc: &DagCnf) {
@@ -59,7 +78,72 @@ impl DagCnfSimulation {
             if dc.is_leaf(v) {
                 continue;
             }
-            self.simulate_var(v, dc);
+            self.sim[v] = self.eval_var(v, dc);
+        }
+    }
+
+    /// Same as `simulate`, evaluating the vars of each topological level on
+    /// `num_thread` threads.
+    pub fn simulate_parallel(&mut self, dc: &DagCnf, num_thread: usize) {
+        let level = dc.level();
+        let mut levels: Vec<Vec<Var>> = Vec::new();
+        for v in dc.var_iter_woc().filter(|&v| !dc.is_leaf(v)) {
+            if levels.len() <= level[v] {
+                levels.resize(level[v] + 1, Vec::new());
+            }
+            levels[level[v]].push(v);
+        }
+        for vars in levels.iter().filter(|l| !l.is_empty()) {
+            let chunk = vars.len().div_ceil(num_thread.max(1));
+            let this = &*self;
+            let res: Vec<Vec<(Var, BitVec)>> = thread::scope(|s| {
+                let handles: Vec<_> = vars
+                    .chunks(chunk)
+                    .map(|c| {
+                        s.spawn(move || c.iter().map(|&v| (v, this.eval_var(v, dc))).collect())
+                    })
+                    .collect();
+                handles.into_iter().map(|h| h.join().unwrap()).collect()
+            });
+            for (v, val) in res.into_iter().flatten() {
+                self.sim[v] = val;
+            }
+        }
+    }
+
+    /// Re-simulate only the fanout cone of `changed`, the vars whose values
+    /// or relations have changed since the last simulation. Vars added to
+    /// `dc` since then are simulated as well, new leaves get random values.
+    pub fn resimulate(&mut self, dc: &DagCnf, changed: impl IntoIterator<Item = impl Into<Var>>) {
+        let mut dirty: VarMap<bool> = VarMap::new_with(dc.max_var());
+        for v in changed.into_iter().map(|v| v.into()) {
+            dirty[v] = true;
+        }
+        let old = Var::new(self.sim.len() - 1);
+        if dc.max_var() > old {
+            let num_word = self.num_bit().div_ceil(BitVec::WORD_SIZE);
+            self.sim.reserve(dc.max_var());
+            let mut rng = self.rng();
+            for v in VarRange::new_inclusive(old + 1, dc.max_var()) {
+                dirty[v] = true;
+                if dc.is_leaf(v) {
+                    let r = BitVec::new_rand(num_word, &mut rng);
+                    let mut x = BitVec::from_elem(self.num_bit(), false);
+                    for i in 0..self.num_bit() {
+                        x.set(i, r.get(i));
+                    }
+                    self.sim[v] = x;
+                }
+            }
+        }
+        for v in dc.var_iter_woc() {
+            if !dirty[v] && !dc.dep[v].iter().any(|&d| dirty[d]) {
+                continue;
+            }
+            dirty[v] = true;
+            if !dc.is_leaf(v) {
+                self.sim[v] = self.eval_var(v, dc);
+            }
         }
     }
 
@@ -72,9 +156,80 @@ impl DagCnfSimulation {
     }
 }
 
+impl DagCnfSimulation {
+    /// Append `num` patterns whose leaf values are given by `leaf(v, i)` for
+    /// the i-th pattern, or random if it returns None, and compute the values
+    /// of the other vars.
+    fn add_leaf_patterns(
+        &mut self,
+        dc: &DagCnf,
+        num: usize,
+        mut leaf: impl FnMut(Var, usize) -> Option<bool>,
+    ) {
+        assert_eq!(self.sim.len(), dc.num_var());
+        let mut rng = self.rng();
+        let mut sim = VarMap::new_with(dc.max_var());
+        for v in dc.var_iter() {
+            let is_leaf = !v.is_constant() && dc.is_leaf(v);
+            sim[v] = BitVec::from_elem(num, false);
+            if is_leaf {
+                for i in 0..num {
+                    sim[v].set(i, leaf(v, i).unwrap_or_else(|| rng.random()));
+                }
+            }
+        }
+        // only the new patterns are simulated, then appended
+        let mut new = DagCnfSimulation { sim, seed: 0 };
+        new.simulate(dc);
+        for v in dc.var_iter() {
+            for i in 0..num {
+                self.sim[v].push(new.sim[v].get(i));
+            }
+        }
+    }
+
+    /// Append one pattern per assignment of the leaves, unassigned leaves
+    /// are filled randomly.
+    pub fn add_assigns(
+        &mut self,
+        dc: &DagCnf,
+        assigns: impl IntoIterator<Item = impl Borrow<VarAssign>>,
+    ) {
+        let assigns: Vec<_> = assigns.into_iter().collect();
+        self.add_leaf_patterns(dc, assigns.len(), |v, i| {
+            let a = assigns[i].borrow();
+            (v <= a.max_var()).then(|| a.v(v.lit()).into()).flatten()
+        });
+    }
+
+    /// Append the patterns given as columns of the leaves in `vbv`, leaves
+    /// without a column are filled randomly.
+    pub fn add_columns(&mut self, dc: &DagCnf, vbv: &VarBitVec) {
+        let num = vbv.bv_len();
+        self.add_leaf_patterns(dc, num, |v, i| {
+            (v <= vbv.max_var() && vbv[v].len() == num).then(|| vbv[v].get(i))
+        });
+    }
+
+    /// Append the model of the last satisfiable call of `solver`, whose vars
+    /// are the vars of `dc`.
+    pub fn add_model(&mut self, dc: &DagCnf, solver: &impl Satif) {
+        self.add_leaf_patterns(dc, 1, |v, _| {
+            (v <= solver.max_var())
+                .then(|| solver.sat_value(v.lit()))
+                .flatten()
+        });
+    }
+}
+
 impl DagCnf {
+    #[inline]
     pub fn simulation(&self, num_word: usize) -> DagCnfSimulation {
-        let mut rng = StdRng::seed_from_u64(0);
+        self.simulation_with_seed(num_word, 0)
+    }
+
+    pub fn simulation_with_seed(&self, num_word: usize, seed: u64) -> DagCnfSimulation {
+        let mut rng = StdRng::seed_from_u64(seed);
         let mut sim = VarMap::new_with(self.max_var());
         sim[Var::CONST] = BitVec::from_elem(num_word * BitVec::WORD_SIZE, false);
         let mut leafs = GHashSet::new();
@@ -92,8 +247,73 @@ impl DagCnf {
                 sim[v] = BitVec::from_elem(num_word * BitVec::WORD_SIZE, false);
             }
         }
-        let mut s = DagCnfSimulation { sim };
+        let mut s = DagCnfSimulation { sim, seed };
         s.simulate(self);
         s
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{DagCnf, Lit, LitVvec, Var, VarAssign};
+
+    #[test]
+    fn test_incremental() {
+        let mut dc = DagCnf::new();
+        dc.new_var_to(Var(3));
+        let (a, b, c) = (Lit::from(1), Lit::from(2), Lit::from(3));
+        let x = dc.new_and([a, b]);
+        let y = dc.new_xor(x, c);
+        let mut sim = dc.simulation_with_seed(2, 7);
+        let mut psim = sim.clone();
+        psim.simulate_parallel(&dc, 4);
+        assert_eq!(psim[y.var()], sim[y.var()]);
+        dc.set_rel(x.var(), &LitVvec::cnf_or(x, &[a, b]));
+        let z = dc.new_and([y, c]);
+        sim.resimulate(&dc, [x]);
+        let full = dc.simulation_with_seed(2, 7);
+        for v in [x, y, z] {
+            assert_eq!(sim[v.var()], full[v.var()]);
+        }
+    }
+
+    #[test]
+    fn test_add_assigns() {
+        let mut dc = DagCnf::new();
+        dc.new_var_to(Var(2));
+        let (a, b) = (Lit::from(1), Lit::from(2));
+        let x = dc.new_and([a, b]);
+        let mut sim = dc.simulation(1);
+        let n = sim.num_bit();
+        let mut p0 = VarAssign::new_with(Var(2));
+        p0.set(a);
+        p0.set(b);
+        let mut p1 = VarAssign::new_with(Var(1));
+        p1.set(!a);
+        sim.add_assigns(&dc, [p0, p1]);
+        assert_eq!(sim.num_bit(), n + 2);
+        assert!(sim[x.var()].get(n));
+        assert!(!sim[x.var()].get(n + 1));
+        assert!(!sim[Var(1)].get(n + 1));
+    }
+
+    #[test]
+    fn test_add_only_new() {
+        let mut dc = DagCnf::new();
+        dc.new_var_to(Var(2));
+        let (a, b) = (Lit::from(1), Lit::from(2));
+        let x = dc.new_and([a, b]);
+        let mut sim = dc.simulation(1);
+        let old = sim[x.var()].clone();
+        let n = sim.num_bit();
+        dc.set_rel(x.var(), &LitVvec::cnf_or(x, &[a, b]));
+        let mut p = VarAssign::new_with(Var(2));
+        p.set(a);
+        p.set(!b);
+        sim.add_assigns(&dc, [p]);
+        for i in 0..n {
+            assert_eq!(sim[x.var()].get(i), old.get(i));
+        }
+        assert!(sim[x.var()].get(n));
+    }
+}
@@ -0,0 +1,216 @@
+use super::{DagCnf, gate::GATE_TT_MAX_SUPPORT};
+use crate::{Lbool, LboolVec, Lit, Var, VarAssign, VarMap, VarRange};
+use giputils::bitvec::BitVec;
+use std::ops::Index;
+
+/// Bit-parallel three-valued simulation, each var holds one `Lbool` per
+/// pattern.
+#[derive(Clone, Debug)]
+pub struct DagCnfTernarySimulation {
+    sim: VarMap<LboolVec>,
+}
+
+impl Index<Var> for DagCnfTernarySimulation {
+    type Output = LboolVec;
+
+    #[inline]
+    fn index(&self, var: Var) -> &Self::Output {
+        &self.sim[var]
+    }
+}
+
+impl DagCnfTernarySimulation {
+    /// All vars are X in all `num_pattern` patterns.
+    pub fn new(dc: &DagCnf, num_pattern: usize) -> Self {
+        let mut sim = VarMap::new_with(dc.max_var());
+        for v in dc.var_iter() {
+            sim[v] = LboolVec::from_elem(Lbool::NONE, num_pattern);
+        }
+        sim[Var::CONST] = LboolVec::from_elem(Lbool::FALSE, num_pattern);
+        Self { sim }
+    }
+
+    #[inline]
+    pub fn num_pattern(&self) -> usize {
+        self.sim[Var::CONST].len()
+    }
+
+    #[inline]
+    pub fn set(&mut self, v: Var, val: LboolVec) {
+        assert_eq!(val.len(), self.num_pattern());
+        self.sim[v] = val;
+    }
+
+    #[inline]
+    pub fn val(&self, lit: Lit) -> LboolVec {
+        if lit.polarity() {
+            self.sim[lit.var()].clone()
+        } else {
+            !&self.sim[lit.var()]
+        }
+    }
+
+    /// Patterns in which `lit` is definitely false.
+    #[inline]
+    fn def_false(&self, lit: Lit) -> BitVec {
+        let x = &self.sim[lit.var()];
+        if lit.polarity() {
+            &!x.v() & x.mask()
+        } else {
+            x.v() & x.mask()
+        }
+    }
+
+    /// `v` is 1 (0) in the patterns where every completion of the X deps
+    /// consistent with `tt` gives 1 (0), and X elsewhere.
+    fn eval_tt(&self, dep: &[Var], tt: u64) -> LboolVec {
+        let n = self.num_pattern();
+        let mut can_one = BitVec::from_elem(n, false);
+        let mut can_zero = BitVec::from_elem(n, false);
+        for a in 0..1usize << dep.len() {
+            let mut consistent = BitVec::from_elem(n, true);
+            for (i, d) in dep.iter().enumerate() {
+                consistent &= &!&self.def_false(d.lit().not_if(a >> i & 1 == 0));
+            }
+            if tt >> a & 1 == 1 {
+                can_one |= &consistent;
+            } else {
+                can_zero |= &consistent;
+            }
+        }
+        let m = &(&can_one | &can_zero) & &!&(&can_one & &can_zero);
+        LboolVec::from_parts(&can_one & &m, m)
+    }
+
+    /// A clause forces the literal of `v` in the patterns where all its
+    /// other literals are definitely false. `v` is X where no clause or
+    /// clauses of both polarities force it.
+    fn eval_clauses(&self, v: Var, dc: &DagCnf) -> LboolVec {
+        let n = self.num_pattern();
+        let mut one = BitVec::from_elem(n, false);
+        let mut zero = BitVec::from_elem(n, false);
+        for rel in dc.cnf[v].iter() {
+            let mut force = BitVec::from_elem(n, true);
+            let mut vl = None;
+            for &l in rel.iter() {
+                if l.var() == v {
+                    vl = Some(l);
+                } else {
+                    force &= &self.def_false(l);
+                }
+            }
+            if vl.unwrap().polarity() {
+                one |= &force;
+            } else {
+                zero |= &force;
+            }
+        }
+        let m = &(&one | &zero) & &!&(&one & &zero);
+        LboolVec::from_parts(&one & &m, m)
+    }
+
+    /// Relations of at most `GATE_TT_MAX_SUPPORT` deps are evaluated on
+    /// their truth table, larger ones clause by clause.
+    fn eval_var(&self, v: Var, dc: &DagCnf) -> LboolVec {
+        let dep: Vec<Var> = dc.dep[v]
+            .iter()
+            .copied()
+            .filter(|d| !d.is_constant())
+            .collect();
+        if dep.len() <= GATE_TT_MAX_SUPPORT
+            && let Some(tt) = dc.truth_table(v, &dep)
+        {
+            return self.eval_tt(&dep, tt);
+        }
+        self.eval_clauses(v, dc)
+    }
+
+    /// Propagate the values of the leaves through the relations. Relations
+    /// with a small support get the most precise value, X only if the X deps
+    /// can change the result. Larger ones are exact when the relation holds
+    /// all its prime implicates, and over-approximate X otherwise, e.g.
+    /// `cnf_ite` lacks the consensus clauses, so a mux with an X condition
+    /// and equal branches is X.
+    pub fn simulate(&mut self, dc: &DagCnf) {
+        for v in VarRange::new_inclusive(Var(1), dc.max_var()) {
+            if !dc.is_leaf(v) {
+                self.sim[v] = self.eval_var(v, dc);
+            }
+        }
+    }
+}
+
+impl DagCnf {
+    /// Ternary simulation of one pattern, leaves unassigned in `assign` are X.
+    pub fn ternary_simulation(&self, assign: &VarAssign) -> DagCnfTernarySimulation {
+        let mut sim = DagCnfTernarySimulation::new(self, 1);
+        for v in self.var_iter_woc().filter(|&v| self.is_leaf(v)) {
+            sim.set(v, LboolVec::from_elem(assign.v(v.lit()), 1));
+        }
+        sim.simulate(self);
+        sim
+    }
+
+    /// Ternary simulation of many patterns given per leaf, leaves missing in
+    /// `leaves` are X.
+    pub fn ternary_simulation_patterns(
+        &self,
+        num_pattern: usize,
+        leaves: impl IntoIterator<Item = (Var, LboolVec)>,
+    ) -> DagCnfTernarySimulation {
+        let mut sim = DagCnfTernarySimulation::new(self, num_pattern);
+        for (v, val) in leaves {
+            assert!(self.is_leaf(v));
+            sim.set(v, val);
+        }
+        sim.simulate(self);
+        sim
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{DagCnf, LboolVec, Lit, Var, VarAssign};
+
+    #[test]
+    fn test_ternary() {
+        let mut dc = DagCnf::new();
+        dc.new_var_to(Var(3));
+        let (a, b, c) = (Lit::from(1), Lit::from(2), Lit::from(3));
+        let x = dc.new_and([a, b]);
+        let y = dc.new_xor(x, c);
+        let z = dc.new_or([!x, c]);
+        let sim = dc.ternary_simulation_patterns(
+            4,
+            [
+                (Var(1), LboolVec::from("01x1")),
+                (Var(2), LboolVec::from("xxx1")),
+                (Var(3), LboolVec::from("x10x")),
+            ],
+        );
+        assert_eq!(sim[x.var()], LboolVec::from("0xx1"));
+        assert_eq!(sim[y.var()], LboolVec::from("xxxx"));
+        assert_eq!(sim.val(z), LboolVec::from("11xx"));
+        let mut assign = VarAssign::new_with(Var(3));
+        assign.set(!a);
+        let sim = dc.ternary_simulation(&assign);
+        assert_eq!(sim.val(z), LboolVec::from("1"));
+    }
+
+    #[test]
+    fn test_ternary_ite() {
+        let mut dc = DagCnf::new();
+        dc.new_var_to(Var(3));
+        let (c, t, e) = (Lit::from(1), Lit::from(2), Lit::from(3));
+        let m = dc.new_ite(c, t, e);
+        let sim = dc.ternary_simulation_patterns(
+            4,
+            [
+                (Var(1), LboolVec::from("xxx1")),
+                (Var(2), LboolVec::from("10x1")),
+                (Var(3), LboolVec::from("10x0")),
+            ],
+        );
+        assert_eq!(sim.val(m), LboolVec::from("10x1"));
+    }
+}
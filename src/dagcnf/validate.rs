@@ -0,0 +1,317 @@
+use super::DagCnf;
+use crate::{Lit, LitVec, Var, VarMap, satif::Satif};
+
+/// Max support size for which a relation is checked by truth-table enumeration.
+const VALIDATE_TT_MAX_SUPPORT: usize = 12;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RelViolation {
+    /// `dep` is used in the relation of `var` but is not defined before it.
+    /// `add_rel` keeps every dep below its var, so this is only reachable
+    /// for relations built by other means, such as a deserialised DagCnf.
+    DepOrder { var: Var, dep: Var },
+    /// The relations of these vars depend on each other.
+    Cycle(Vec<Var>),
+    /// Under the `witness` assignment of its deps, no value of `var`
+    /// satisfies its relation.
+    NotTotal { var: Var, witness: LitVec },
+    /// Under the `witness` assignment of its deps, both values of `var`
+    /// satisfy its relation.
+    NotDeterministic { var: Var, witness: LitVec },
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ValidationReport {
+    pub violations: Vec<RelViolation>,
+    /// Vars whose support was too large to enumerate while no SAT solver was
+    /// provided.
+    pub unchecked: Vec<Var>,
+}
+
+impl ValidationReport {
+    #[inline]
+    pub fn is_ok(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+/// Support of `v` without the constant var, and the clauses of `v` with the
+/// literal of `v` split off.
+fn rel_split(dc: &DagCnf, v: Var) -> (Vec<Var>, Vec<(Lit, LitVec)>) {
+    let mut dep: Vec<Var> = dc.dep[v]
+        .iter()
+        .copied()
+        .filter(|d| !d.is_constant())
+        .collect();
+    dep.sort();
+    let rel = dc.cnf[v]
+        .iter()
+        .map(|cls| (cls.last(), cls.filter(|l| l.var() != v)))
+        .collect();
+    (dep, rel)
+}
+
+#[inline]
+fn witness(dep: &[Var], a: usize) -> LitVec {
+    dep.iter()
+        .enumerate()
+        .map(|(i, d)| d.lit().not_if(a >> i & 1 == 0))
+        .collect()
+}
+
+fn rel_check_tt(v: Var, dep: &[Var], rel: &[(Lit, LitVec)]) -> Option<RelViolation> {
+    let lit_value = |l: Lit, a: usize| {
+        if l.var().is_constant() {
+            return !l.polarity();
+        }
+        let i = dep.binary_search(&l.var()).unwrap();
+        (a >> i & 1 == 1) == l.polarity()
+    };
+    for a in 0..1usize << dep.len() {
+        let (mut on, mut off) = (false, false);
+        for (vl, rest) in rel.iter() {
+            if rest.iter().all(|&l| !lit_value(l, a)) {
+                if vl.polarity() {
+                    on = true;
+                } else {
+                    off = true;
+                }
+            }
+        }
+        if on && off {
+            return Some(RelViolation::NotTotal {
+                var: v,
+                witness: witness(dep, a),
+            });
+        }
+        if !on && !off {
+            return Some(RelViolation::NotDeterministic {
+                var: v,
+                witness: witness(dep, a),
+            });
+        }
+    }
+    None
+}
+
+fn rel_check_sat<S: Satif>(
+    v: Var,
+    dep: &[Var],
+    rel: &[(Lit, LitVec)],
+    new_solver: &mut impl FnMut() -> S,
+) -> Option<RelViolation> {
+    let local = |l: Lit| {
+        l.map_var(|x| {
+            if x.is_constant() {
+                x
+            } else {
+                Var::new(dep.binary_search(&x).unwrap() + 1)
+            }
+        })
+    };
+    let model = |solver: &S| {
+        dep.iter()
+            .enumerate()
+            .map(|(i, d)| {
+                let b = solver.sat_value(Var::new(i + 1).lit()).unwrap_or(true);
+                d.lit().not_if(!b)
+            })
+            .collect()
+    };
+
+    // both values of v satisfy the relation iff the relation without v is
+    // satisfiable
+    let mut solver = new_solver();
+    solver.new_var_to(Var::new(dep.len()));
+    solver.add_clause(&[Lit::constant(true)]);
+    for (_, rest) in rel.iter() {
+        let cls: LitVec = rest.iter().map(|&l| local(l)).collect();
+        solver.add_clause(&cls);
+    }
+    if solver.solve(&[]) {
+        return Some(RelViolation::NotDeterministic {
+            var: v,
+            witness: model(&solver),
+        });
+    }
+
+    // no value of v satisfies the relation iff a clause of each polarity of v
+    // can be falsified at the same time
+    if !(rel.iter().any(|(vl, _)| vl.polarity()) && rel.iter().any(|(vl, _)| !vl.polarity())) {
+        return None;
+    }
+    let mut solver = new_solver();
+    solver.new_var_to(Var::new(dep.len()));
+    solver.add_clause(&[Lit::constant(true)]);
+    for pol in [true, false] {
+        let mut sel = LitVec::new();
+        for (_, rest) in rel.iter().filter(|(vl, _)| vl.polarity() == pol) {
+            let s = solver.new_var().lit();
+            for &l in rest.iter() {
+                solver.add_clause(&[!s, !local(l)]);
+            }
+            sel.push(s);
+        }
+        solver.add_clause(&sel);
+    }
+    if solver.solve(&[]) {
+        return Some(RelViolation::NotTotal {
+            var: v,
+            witness: model(&solver),
+        });
+    }
+    None
+}
+
+impl DagCnf {
+    fn find_cycles(&self) -> Vec<Vec<Var>> {
+        // 0: unvisited, 1: on stack, 2: done
+        let mut mark: VarMap<u8> = VarMap::new_with(self.max_var);
+        let mut cycles = Vec::new();
+        for root in self.var_iter() {
+            if mark[root] != 0 {
+                continue;
+            }
+            let mut stack = vec![(root, 0)];
+            mark[root] = 1;
+            while let Some((v, i)) = stack.last_mut() {
+                let v = *v;
+                if *i == self.dep[v].len() {
+                    mark[v] = 2;
+                    stack.pop();
+                    continue;
+                }
+                let d = self.dep[v][*i];
+                *i += 1;
+                match mark[d] {
+                    0 => {
+                        mark[d] = 1;
+                        stack.push((d, 0));
+                    }
+                    1 => {
+                        let p = stack.iter().position(|(x, _)| *x == d).unwrap();
+                        cycles.push(stack[p..].iter().map(|(x, _)| *x).collect());
+                    }
+                    _ => (),
+                }
+            }
+        }
+        cycles
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn validate_inner(
+        &self,
+        mut large_check: Option<
+            &mut dyn FnMut(Var, &[Var], &[(Lit, LitVec)]) -> Option<RelViolation>,
+        >,
+    ) -> ValidationReport {
+        let mut report = ValidationReport::default();
+        let mut ordered = true;
+        for v in self.var_iter_woc() {
+            for &d in self.dep[v].iter() {
+                if d >= v {
+                    ordered = false;
+                    report
+                        .violations
+                        .push(RelViolation::DepOrder { var: v, dep: d });
+                }
+            }
+        }
+        if !ordered {
+            for c in self.find_cycles() {
+                report.violations.push(RelViolation::Cycle(c));
+            }
+        }
+        for v in self.var_iter_woc() {
+            if self.is_leaf(v) {
+                continue;
+            }
+            let (dep, rel) = rel_split(self, v);
+            let violation = if dep.len() <= VALIDATE_TT_MAX_SUPPORT {
+                rel_check_tt(v, &dep, &rel)
+            } else if let Some(large_check) = &mut large_check {
+                large_check(v, &dep, &rel)
+            } else {
+                report.unchecked.push(v);
+                continue;
+            };
+            report.violations.extend(violation);
+        }
+        report
+    }
+
+    /// Check that the relations are acyclic, and that every relation defines
+    /// a total and deterministic function of its deps. Relations with large
+    /// support are reported as unchecked.
+    pub fn validate(&self) -> ValidationReport {
+        self.validate_inner(None)
+    }
+
+    /// Same as `validate`, but relations with large support are checked with
+    /// fresh solvers created by `new_solver`.
+    pub fn validate_with_sat<S: Satif>(
+        &self,
+        mut new_solver: impl FnMut() -> S,
+    ) -> ValidationReport {
+        self.validate_inner(Some(&mut |v, dep, rel| {
+            rel_check_sat(v, dep, rel, &mut new_solver)
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::RelViolation;
+    use crate::{DagCnf, Lit, LitVec, Var, satif::test::DpllSolver};
+
+    #[test]
+    fn test_validate() {
+        let mut dc = DagCnf::new();
+        dc.new_var_to(Var(2));
+        let (a, b) = (Lit::from(1), Lit::from(2));
+        dc.new_and([a, b]);
+        dc.new_xor(a, b);
+        assert!(dc.validate().is_ok());
+
+        let n = dc.new_var();
+        dc.add_rel(n, &[LitVec::from([a, n.lit()])]);
+        let n = dc.new_var();
+        dc.add_rel(n, &[LitVec::from([n.lit()]), LitVec::from([a, !n.lit()])]);
+        let report = dc.validate();
+        assert_eq!(
+            report.violations,
+            vec![
+                RelViolation::NotDeterministic {
+                    var: Var(5),
+                    witness: LitVec::from([a]),
+                },
+                RelViolation::NotTotal {
+                    var: Var(6),
+                    witness: LitVec::from([!a]),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_with_sat() {
+        let mut dc = DagCnf::new();
+        dc.new_var_to(Var(13));
+        let ins: LitVec = (1..=13).map(Lit::from).collect();
+        dc.new_and(ins.clone());
+        let n = dc.new_var();
+        let mut cls = ins.clone();
+        cls.push(n.lit());
+        dc.add_rel(n, &[cls]);
+        assert_eq!(dc.validate().unchecked, vec![Var(14), n]);
+        let report = dc.validate_with_sat(DpllSolver::new);
+        assert!(report.unchecked.is_empty());
+        let [RelViolation::NotDeterministic { var, witness }] = &report.violations[..] else {
+            panic!()
+        };
+        assert_eq!(*var, n);
+        assert_eq!(witness.len(), 13);
+        assert!(witness.iter().any(|l| ins.contains(l)));
+    }
+}
@@ -24,39 +24,19 @@ pub fn var_bitblast(sort: Sort) -> TermVec {
 
 impl Term {
     pub fn bitblast(&self, map: &mut GHashMap<Term, TermVec>) -> TermVec {
-        if let Some(res) = map.get(self) {
-            return res.clone();
-        }
-        let blast = match self.deref() {
+        self.cached_fold(map, |t, terms| match t.deref() {
             TermType::Const(const_term) => bv_const_bitblast(const_term),
-            TermType::Var(_) => var_bitblast(self.sort()),
-            TermType::Op(op_term) => {
-                let terms: Vec<TermVec> = op_term.terms.iter().map(|s| s.bitblast(map)).collect();
-                op_term.op.bitblast(&terms)
-            }
-        };
-        map.insert(self.clone(), blast.clone());
-        map.get(self).unwrap().clone()
+            TermType::Var(_) => var_bitblast(t.sort()),
+            TermType::Op(op_term) => op_term.op.bitblast(terms),
+        })
     }
 
     pub fn cnf_encode(&self, dc: &mut DagCnf, map: &mut GHashMap<Term, Lit>) -> Lit {
-        if let Some(res) = map.get(self) {
-            return *res;
-        }
-        let blast = match self.deref() {
+        self.cached_fold(map, |t, terms| match t.deref() {
             TermType::Const(const_term) => bv_const_cnf_encode(const_term),
             TermType::Var(_) => dc.new_var().lit(),
-            TermType::Op(op_term) => {
-                let terms: Vec<Lit> = op_term
-                    .terms
-                    .iter()
-                    .map(|s| s.cnf_encode(dc, map))
-                    .collect();
-                op_term.op.cnf_encode(dc, &terms)
-            }
-        };
-        map.insert(self.clone(), blast);
-        *map.get(self).unwrap()
+            TermType::Op(op_term) => op_term.op.cnf_encode(dc, terms),
+        })
     }
 }
 
@@ -0,0 +1,83 @@
+use super::{
+    FolOp, Sort, Term, TermPostOrder, TermSymbol, TermType,
+    term_mgr::{SerializedOpTerm, SerializedTermType},
+};
+use giputils::hash::GHashMap;
+use serde::{Deserialize, Serialize};
+use std::ops::Deref;
+
+/// Self-contained copy of the cone of some roots, independent of any
+/// TermManager. Terms are stored in post-order and refer to their operands
+/// by index, vars are numbered by their order in the graph.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TermGraph {
+    terms: Vec<(Sort, SerializedTermType)>,
+    roots: Vec<usize>,
+    symbols: Vec<(usize, String)>,
+}
+
+impl TermGraph {
+    /// The cone of `roots`, with the symbols of its terms in `symbols`.
+    pub fn export(roots: impl IntoIterator<Item = impl AsRef<Term>>, symbols: &TermSymbol) -> Self {
+        let roots: Vec<Term> = roots.into_iter().map(|t| t.as_ref().clone()).collect();
+        let mut graph = Self::default();
+        let mut index = GHashMap::new();
+        let mut num_var = 0;
+        for t in TermPostOrder::new(&roots) {
+            let ty = match t.deref() {
+                TermType::Const(c) => SerializedTermType::Const(c.clone()),
+                TermType::Var(_) => {
+                    num_var += 1;
+                    SerializedTermType::Var(num_var - 1)
+                }
+                TermType::Op(op_term) => SerializedTermType::Op(SerializedOpTerm {
+                    op: op_term.op.to_string(),
+                    terms: op_term.terms.iter().map(|s| index[s]).collect(),
+                }),
+            };
+            for s in symbols.get(&t).into_iter().flatten() {
+                graph.symbols.push((graph.terms.len(), s.clone()));
+            }
+            index.insert(t.clone(), graph.terms.len());
+            graph.terms.push((t.sort(), ty));
+        }
+        graph.roots = roots.iter().map(|r| index[r]).collect();
+        graph
+    }
+
+    /// Rebuild the graph in the TermManager of this thread, with fresh vars.
+    /// Ops are made again by `Term::new_op`, so they are normalised as the
+    /// terms of this manager. Returns the roots and the symbols of the graph.
+    pub fn import(&self) -> (Vec<Term>, TermSymbol) {
+        let mut terms: Vec<Term> = Vec::with_capacity(self.terms.len());
+        for (sort, ty) in self.terms.iter() {
+            let t = match ty {
+                SerializedTermType::Const(c) => Term::bv_const(c.clone()),
+                SerializedTermType::Var(_) => Term::new_var(*sort),
+                SerializedTermType::Op(op) => Term::new_op(
+                    FolOp::from(op.op.as_str()),
+                    op.terms.iter().map(|&s| &terms[s]),
+                ),
+            };
+            terms.push(t);
+        }
+        let mut symbols = TermSymbol::new();
+        for (t, s) in self.symbols.iter() {
+            symbols.add_symbol(&terms[*t], s);
+        }
+        (
+            self.roots.iter().map(|&r| terms[r].clone()).collect(),
+            symbols,
+        )
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.terms.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+}
@@ -1,4 +1,5 @@
 pub mod bitblast;
+mod graph;
 mod op;
 mod replace;
 pub mod simplify;
@@ -8,12 +9,17 @@ mod term;
 mod term_mgr;
 #[cfg(test)]
 mod test;
+mod ts;
 mod utils;
 mod value;
+mod visit;
 
+pub use graph::*;
 pub use op::*;
 pub use sort::*;
 pub use term::*;
 pub use term_mgr::*;
+pub use ts::*;
 pub use utils::*;
 pub use value::*;
+pub use visit::*;
@@ -1,15 +1,35 @@
-use super::{Term, TermType};
-use std::ops::Deref;
+use super::{Term, TermVec};
+use giputils::hash::GHashMap;
 
 impl Term {
     pub fn replace(&self, x: &Term, y: &Term) -> Term {
-        if self.eq(x) {
-            return y.clone();
-        }
-        let TermType::Op(op) = self.deref() else {
-            return self.clone();
-        };
-        let terms: Vec<_> = op.terms.iter().map(|t| t.replace(x, y)).collect();
-        Term::new_op(op.op, &terms)
+        self.substitute(&GHashMap::from_iter([(x.clone(), y.clone())]))
+    }
+
+    /// Replace all the terms in `map` at once, the replacements are not
+    /// substituted again.
+    #[inline]
+    pub fn substitute(&self, map: &GHashMap<Term, Term>) -> Term {
+        self.cached_substitute(map, &mut GHashMap::new())
+    }
+
+    /// Same as `substitute`, with the results of the subterms memoised in
+    /// `cache`, which must only be shared between calls with the same `map`.
+    pub fn cached_substitute(
+        &self,
+        map: &GHashMap<Term, Term>,
+        cache: &mut GHashMap<Term, Term>,
+    ) -> Term {
+        self.cached_rewrite(&mut |t: &Term| map.get(t).cloned(), cache)
+    }
+}
+
+impl TermVec {
+    /// `Term::substitute` on every term, sharing one cache.
+    pub fn substitute(&self, map: &GHashMap<Term, Term>) -> TermVec {
+        let mut cache = GHashMap::new();
+        self.iter()
+            .map(|t| t.cached_substitute(map, &mut cache))
+            .collect()
     }
 }
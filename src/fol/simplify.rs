@@ -86,14 +86,17 @@ fn msb_bit_term(term: &Term) -> Option<Term> {
 }
 
 fn collect_assoc_terms(op: FolOp, term: &Term, out: &mut Vec<Term>) {
-    if let Some(top) = term.try_op()
-        && top.op == op
-    {
-        collect_assoc_terms(op, &top[0], out);
-        collect_assoc_terms(op, &top[1], out);
-        return;
+    let mut stack = vec![term.clone()];
+    while let Some(t) = stack.pop() {
+        if let Some(top) = t.try_op()
+            && top.op == op
+        {
+            stack.push(top[1].clone());
+            stack.push(top[0].clone());
+            continue;
+        }
+        out.push(t);
     }
-    out.push(term.clone());
 }
 
 fn eval_const_op(op: FolOp, terms: &[Term]) -> Term {
@@ -2435,24 +2438,54 @@ impl Term {
     }
 
     pub fn simplify_with_ctx(&self, ctx: &SimplifyCtx, map: &mut GHashMap<Term, Term>) -> Term {
-        if let Some(res) = map.get(self) {
-            return res.clone();
-        }
-        let simp = if let Some(op_term) = self.try_op() {
-            let terms: Vec<Term> = op_term
-                .terms
-                .iter()
-                .map(|s| s.simplify_with_ctx(ctx, map))
-                .collect();
-            if let Some(res) = op_term.op.simplify(ctx, &terms) {
-                res.simplify_with_ctx(ctx, map)
-            } else {
-                Term::new_op(op_term.op, terms)
+        enum Visit {
+            Enter,
+            Rewrite,
+            /// Result of the rewritten term, simplified again.
+            Resimplify(Term),
+        }
+        let mut stack = vec![(self.clone(), Visit::Enter)];
+        while let Some((t, visit)) = stack.pop() {
+            if map.contains_key(&t) {
+                continue;
             }
-        } else {
-            self.clone()
-        };
-        map.insert(self.clone(), simp);
-        map.get(self).unwrap().clone()
+            let Some(op_term) = t.try_op() else {
+                map.insert(t.clone(), t);
+                continue;
+            };
+            match visit {
+                Visit::Enter => {
+                    stack.push((t.clone(), Visit::Rewrite));
+                    for s in op_term.terms.iter().rev() {
+                        if !map.contains_key(s) {
+                            stack.push((s.clone(), Visit::Enter));
+                        }
+                    }
+                }
+                Visit::Rewrite => {
+                    let terms: Vec<Term> = op_term.terms.iter().map(|s| map[s].clone()).collect();
+                    match op_term.op.simplify(ctx, &terms) {
+                        Some(res) => match map.get(&res) {
+                            Some(r) => {
+                                map.insert(t.clone(), r.clone());
+                            }
+                            None => {
+                                stack.push((t.clone(), Visit::Resimplify(res.clone())));
+                                stack.push((res, Visit::Enter));
+                            }
+                        },
+                        None => {
+                            let r = Term::new_op(op_term.op, terms);
+                            map.insert(t.clone(), r);
+                        }
+                    }
+                }
+                Visit::Resimplify(res) => {
+                    let r = map[&res].clone();
+                    map.insert(t, r);
+                }
+            }
+        }
+        map[self].clone()
     }
 }
@@ -3,20 +3,24 @@ use super::{op::FolOp, sort::Sort};
 use crate::OptLevel;
 use crate::fol::op::{Concat, Slice};
 use crate::fol::simplify::SimplifyCtx;
-use crate::fol::{OpTrait, TermVec, Value, op, term_mgr};
+use crate::fol::{OpTrait, TermVec, Value, op, with_term_mgr};
 use giputils::bitvec::BitVec;
 use giputils::hash::GHashMap;
+#[cfg(not(feature = "sync"))]
 use giputils::ptr::Grc;
 use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
 use std::fmt::{self, Debug};
 use std::hash;
 use std::iter::once;
+use std::mem::take;
 use std::ops::Index;
+#[cfg(feature = "sync")]
+use std::sync::Arc;
 use std::{hash::Hash, ops::Deref};
 
 #[derive(Clone)]
 pub struct Term {
-    pub(super) inner: Grc<TermInner>,
+    pub(super) inner: TermPtr,
 }
 
 impl Term {
@@ -27,13 +31,13 @@ impl Term {
 
     #[inline]
     pub fn bool_const(c: bool) -> Term {
-        term_mgr().new_term(TermType::Const(BitVec::from(&[c])), Sort::Bv(1))
+        with_term_mgr(|m| m.new_term(TermType::Const(BitVec::from(&[c])), Sort::Bv(1)))
     }
 
     #[inline]
     pub fn bv_const(c: BitVec) -> Term {
         let sort = Sort::Bv(c.len());
-        term_mgr().new_term(TermType::Const(c), sort)
+        with_term_mgr(|m| m.new_term(TermType::Const(c), sort))
     }
 
     #[inline]
@@ -56,12 +60,12 @@ impl Term {
         }
         let sort = op.sort(&terms);
         let term = TermType::Op(OpTerm::new(op, terms));
-        term_mgr().new_term(term, sort)
+        with_term_mgr(|m| m.new_term(term, sort))
     }
 
     #[inline]
     pub fn new_var(sort: Sort) -> Term {
-        term_mgr().new_var(sort)
+        with_term_mgr(|m| m.new_var(sort))
     }
 
     #[inline]
@@ -244,25 +248,7 @@ impl Term {
         r: &impl Fn(&Term) -> Option<Term>,
         map: &mut GHashMap<Term, Term>,
     ) -> Term {
-        if let Some(r) = map.get(self) {
-            return r.clone();
-        }
-        let r = if let Some(r) = r(self) {
-            r
-        } else {
-            self.try_op()
-                .map(|op_term| {
-                    let a: Vec<Term> = op_term
-                        .terms
-                        .iter()
-                        .map(|t| t.cached_apply(r, map))
-                        .collect();
-                    Term::new_op(op_term.op, a)
-                })
-                .unwrap_or(self.clone())
-        };
-        map.insert(self.clone(), r.clone());
-        r
+        self.cached_rewrite(&mut |t: &Term| r(t), map)
     }
 
     pub fn apply(&self, r: &impl Fn(&Term) -> Option<Term>) -> Term {
@@ -270,20 +256,11 @@ impl Term {
     }
 
     pub fn simulate(&self, val: &mut GHashMap<Term, Value>) -> Value {
-        if let Some(v) = val.get(self) {
-            return v.clone();
-        }
-        let v = match self.deref() {
+        self.cached_fold(val, |t, vals| match t.deref() {
             TermType::Const(c) => Value::Bv(c.clone().into()),
-            TermType::Var(_) => Value::default_from(self.sort()),
-            TermType::Op(op_term) => {
-                let child_vals: Vec<Value> =
-                    op_term.terms.iter().map(|t| t.simulate(val)).collect();
-                op_term.op.simulate(&child_vals)
-            }
-        };
-        val.insert(self.clone(), v.clone());
-        v
+            TermType::Var(_) => Value::default_from(t.sort()),
+            TermType::Op(op_term) => op_term.op.simulate(vals),
+        })
     }
 }
 
@@ -326,8 +303,7 @@ impl<'de> Deserialize<'de> for Term {
         D: Deserializer<'de>,
     {
         let id = usize::deserialize(deserializer)?;
-        term_mgr()
-            .get_term_by_id(id)
+        with_term_mgr(|m| m.get_term_by_id(id))
             .ok_or_else(|| de::Error::custom(format!("unknown term id {id}")))
     }
 }
@@ -357,10 +333,18 @@ impl AsRef<Term> for Term {
 }
 
 impl Drop for Term {
-    #[inline]
+    /// Free the subterms that are only held by this term on an explicit
+    /// stack, as dropping a deep term recursively overflows the stack.
     fn drop(&mut self) {
-        // let g = self.clone();
-        // tm().tgc.collect(g);
+        let Some(TermType::Op(op_term)) = self.inner.get_mut().map(|i| &mut i.ty) else {
+            return;
+        };
+        let mut stack = take(&mut op_term.terms);
+        while let Some(mut t) = stack.pop() {
+            if let Some(TermType::Op(op_term)) = t.inner.get_mut().map(|i| &mut i.ty) {
+                stack.append(&mut op_term.terms);
+            }
+        }
     }
 }
 
@@ -441,6 +425,80 @@ pub(super) struct TermInner {
     pub(super) ty: TermType,
 }
 
+/// Counted pointer to a term node, `Grc` by default and `Arc` with the
+/// `sync` feature, so that terms can be sent and shared between threads.
+#[derive(Clone)]
+pub(super) struct TermPtr {
+    #[cfg(not(feature = "sync"))]
+    p: Grc<TermInner>,
+    #[cfg(feature = "sync")]
+    p: Arc<TermInner>,
+}
+
+#[cfg(not(feature = "sync"))]
+impl TermPtr {
+    #[inline]
+    pub(super) fn new(inner: TermInner) -> Self {
+        Self { p: Grc::new(inner) }
+    }
+
+    #[inline]
+    pub(super) fn as_ptr(&self) -> *const TermInner {
+        self.p.as_ptr()
+    }
+
+    #[inline]
+    pub(super) fn count(&self) -> usize {
+        self.p.count()
+    }
+
+    /// The node if this is its only reference.
+    #[inline]
+    fn get_mut(&mut self) -> Option<&mut TermInner> {
+        (self.p.count() == 1).then_some(&mut *self.p)
+    }
+}
+
+#[cfg(feature = "sync")]
+impl TermPtr {
+    #[inline]
+    pub(super) fn new(inner: TermInner) -> Self {
+        Self { p: Arc::new(inner) }
+    }
+
+    #[inline]
+    pub(super) fn as_ptr(&self) -> *const TermInner {
+        Arc::as_ptr(&self.p)
+    }
+
+    #[inline]
+    pub(super) fn count(&self) -> usize {
+        Arc::strong_count(&self.p)
+    }
+
+    /// The node if this is its only reference.
+    #[inline]
+    fn get_mut(&mut self) -> Option<&mut TermInner> {
+        Arc::get_mut(&mut self.p)
+    }
+}
+
+impl Deref for TermPtr {
+    type Target = TermInner;
+
+    #[inline]
+    fn deref(&self) -> &TermInner {
+        &self.p
+    }
+}
+
+impl PartialEq for TermPtr {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.as_ptr() == other.as_ptr()
+    }
+}
+
 impl TermInner {
     #[inline]
     pub fn sort(&self) -> Sort {
@@ -1,12 +1,32 @@
-use super::{FolOp, OpTerm, Sort, Term, TermInner, TermType};
+//! Hash-consing of terms. Each thread has its own `TermManager`, reached
+//! through `with_term_mgr` or replaced for a scope by `scoped_term_mgr`.
+//!
+//! With the `sync` feature, terms are atomically counted, so they can be
+//! sent and shared between threads, and a `SyncTermManager` hash-conses the
+//! terms of many threads in independently locked shards. A thread makes its
+//! terms in it by installing a `TermManager::shared` handle as its manager.
+//! Without the feature, terms are moved between threads as a `TermGraph`.
+
+use super::{FolOp, OpTerm, Sort, Term, TermInner, TermPtr, TermType};
 use giputils::{
     bitvec::BitVec,
     hash::{GHashMap, GHashSet},
-    ptr::Grc,
 };
 use log::debug;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
-use std::{cell::UnsafeCell, ops::Deref};
+use std::{
+    cell::RefCell,
+    mem::{replace, swap},
+    ops::Deref,
+};
+#[cfg(feature = "sync")]
+use std::{
+    hash::{DefaultHasher, Hash, Hasher},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
 
 #[derive(Default, Clone)]
 pub struct TermManager {
@@ -14,6 +34,9 @@ pub struct TermManager {
     avl_tid: usize,
     map: GHashMap<TermType, Term>,
     id2term: Option<GHashMap<usize, Term>>,
+    /// Manager that all the operations are forwarded to, if any.
+    #[cfg(feature = "sync")]
+    shared: Option<Arc<SyncTermManager>>,
 }
 
 impl TermManager {
@@ -24,18 +47,35 @@ impl TermManager {
             avl_tid: 0,
             map: GHashMap::new(),
             id2term: None,
+            #[cfg(feature = "sync")]
+            shared: None,
+        }
+    }
+
+    /// Handle on `shared`: terms are made in and looked up from it, so the
+    /// threads holding handles on the same manager share their terms.
+    #[cfg(feature = "sync")]
+    #[inline]
+    pub fn shared(shared: Arc<SyncTermManager>) -> Self {
+        Self {
+            shared: Some(shared),
+            ..Self::new()
         }
     }
 
     #[inline]
     pub fn new_term(&mut self, ty: TermType, sort: Sort) -> Term {
+        #[cfg(feature = "sync")]
+        if let Some(shared) = &self.shared {
+            return shared.new_term(ty, sort);
+        }
         match self.map.get(&ty) {
             Some(term) => term.clone(),
             None => {
                 let id = self.avl_tid;
                 self.avl_tid += 1;
                 let term = Term {
-                    inner: Grc::new(TermInner {
+                    inner: TermPtr::new(TermInner {
                         id,
                         sort,
                         ty: ty.clone(),
@@ -52,6 +92,10 @@ impl TermManager {
 
     #[inline]
     pub fn new_var(&mut self, sort: Sort) -> Term {
+        #[cfg(feature = "sync")]
+        if let Some(shared) = &self.shared {
+            return shared.new_var(sort);
+        }
         let id = self.avl_vid;
         self.avl_vid += 1;
         let term = TermType::Var(id);
@@ -60,95 +104,254 @@ impl TermManager {
 
     #[inline]
     pub fn len(&self) -> usize {
+        #[cfg(feature = "sync")]
+        if let Some(shared) = &self.shared {
+            return shared.len();
+        }
         self.map.len()
     }
 
     #[inline]
     pub fn is_empty(&self) -> bool {
-        self.map.is_empty()
+        self.len() == 0
     }
 
     #[inline]
     pub fn enable_id_map(&mut self) {
+        #[cfg(feature = "sync")]
+        if let Some(shared) = &self.shared {
+            return shared.enable_id_map();
+        }
         if self.id2term.is_none() {
-            let map = self
-                .map
-                .values()
-                .map(|term| (term.id(), term.clone()))
-                .collect();
-            self.id2term = Some(map);
+            self.id2term = Some(id_map(&[&self.map]));
         }
     }
 
     #[inline]
     pub fn disable_id_map(&mut self) {
+        #[cfg(feature = "sync")]
+        if let Some(shared) = &self.shared {
+            return shared.disable_id_map();
+        }
         self.id2term = None;
     }
 
     #[inline]
     pub fn get_term_by_id(&self, id: usize) -> Option<Term> {
+        #[cfg(feature = "sync")]
+        if let Some(shared) = &self.shared {
+            return shared.get_term_by_id(id);
+        }
         self.id2term.as_ref()?.get(&id).cloned()
     }
 
-    #[inline]
-    fn add_internal_ref(term: &Term, internal_refs: &mut GHashMap<*const TermInner, usize>) {
-        *internal_refs.entry(term.inner.as_ptr()).or_insert(0) += 1;
+    fn garbage_collect(&mut self) {
+        #[cfg(feature = "sync")]
+        if let Some(shared) = &self.shared {
+            return shared.garbage_collect();
+        }
+        let before = self.map.len();
+        let live = live_terms(&[&self.map], self.id2term.as_ref());
+        retain_live(&mut self.map, self.id2term.as_mut(), &live);
+        let after = self.map.len();
+        debug!(
+            "term GC cleared {} terms ({} -> {})",
+            before - after,
+            before,
+            after
+        );
     }
+}
 
-    #[inline]
-    fn add_term_type_internal_refs(
-        ty: &TermType,
-        internal_refs: &mut GHashMap<*const TermInner, usize>,
-    ) {
-        if let TermType::Op(op) = ty {
-            for term in &op.terms {
-                Self::add_internal_ref(term, internal_refs);
-            }
+#[inline]
+fn id_map(maps: &[&GHashMap<TermType, Term>]) -> GHashMap<usize, Term> {
+    maps.iter()
+        .flat_map(|m| m.values())
+        .map(|term| (term.id(), term.clone()))
+        .collect()
+}
+
+#[inline]
+fn add_internal_ref(term: &Term, internal_refs: &mut GHashMap<*const TermInner, usize>) {
+    *internal_refs.entry(term.inner.as_ptr()).or_insert(0) += 1;
+}
+
+#[inline]
+fn add_term_type_internal_refs(
+    ty: &TermType,
+    internal_refs: &mut GHashMap<*const TermInner, usize>,
+) {
+    if let TermType::Op(op) = ty {
+        for term in &op.terms {
+            add_internal_ref(term, internal_refs);
         }
     }
+}
 
-    fn garbage_collect(&mut self) {
-        let before = self.map.len();
-        let mut internal_refs = GHashMap::new();
-        for (ty, term) in self.map.iter() {
-            Self::add_internal_ref(term, &mut internal_refs);
-            Self::add_term_type_internal_refs(ty, &mut internal_refs);
-            Self::add_term_type_internal_refs(term.deref(), &mut internal_refs);
+/// Terms of `maps` reachable from a reference held outside the tables of
+/// the manager and the operands of its terms.
+fn live_terms(
+    maps: &[&GHashMap<TermType, Term>],
+    id2term: Option<&GHashMap<usize, Term>>,
+) -> GHashSet<*const TermInner> {
+    let mut internal_refs = GHashMap::new();
+    for (ty, term) in maps.iter().flat_map(|m| m.iter()) {
+        add_internal_ref(term, &mut internal_refs);
+        add_term_type_internal_refs(ty, &mut internal_refs);
+        add_term_type_internal_refs(term.deref(), &mut internal_refs);
+    }
+    if let Some(id2term) = id2term {
+        for term in id2term.values() {
+            add_internal_ref(term, &mut internal_refs);
         }
-        if let Some(id2term) = &self.id2term {
-            for term in id2term.values() {
-                Self::add_internal_ref(term, &mut internal_refs);
-            }
+    }
+
+    let mut stack: Vec<Term> = maps
+        .iter()
+        .flat_map(|m| m.values())
+        .filter(|term| {
+            let ptr = term.inner.as_ptr();
+            let internal_refs = internal_refs.get(&ptr).copied().unwrap_or(0);
+            term.inner.count() > internal_refs
+        })
+        .cloned()
+        .collect();
+
+    let mut live = GHashSet::new();
+    while let Some(term) = stack.pop() {
+        if !live.insert(term.inner.as_ptr()) {
+            continue;
+        }
+        if let TermType::Op(op) = term.deref() {
+            stack.extend(op.terms.iter().cloned());
         }
+    }
+    live
+}
 
-        let mut stack: Vec<Term> = self
-            .map
-            .values()
-            .filter(|term| {
-                let ptr = term.inner.as_ptr();
-                let internal_refs = internal_refs.get(&ptr).copied().unwrap_or(0);
-                term.inner.count() > internal_refs
-            })
-            .cloned()
-            .collect();
+fn retain_live(
+    map: &mut GHashMap<TermType, Term>,
+    id2term: Option<&mut GHashMap<usize, Term>>,
+    live: &GHashSet<*const TermInner>,
+) {
+    map.retain(|_, term| live.contains(&term.inner.as_ptr()));
+    map.shrink_to_fit();
+    if let Some(id2term) = id2term {
+        id2term.retain(|_, term| live.contains(&term.inner.as_ptr()));
+        id2term.shrink_to_fit();
+    }
+}
 
-        let mut live = GHashSet::new();
-        while let Some(term) = stack.pop() {
-            if !live.insert(term.inner.as_ptr()) {
-                continue;
-            }
-            if let TermType::Op(op) = term.deref() {
-                stack.extend(op.terms.iter().cloned());
-            }
+#[cfg(feature = "sync")]
+const NUM_SHARDS: usize = 64;
+
+/// TermManager shared between threads, see `TermManager::shared`. The table
+/// is split into shards by the hash of the term type, each behind its own
+/// lock, and ids are taken from atomic counters. Locks are always taken in
+/// shard order, then the id map.
+#[cfg(feature = "sync")]
+pub struct SyncTermManager {
+    avl_vid: AtomicUsize,
+    avl_tid: AtomicUsize,
+    shards: Vec<Mutex<GHashMap<TermType, Term>>>,
+    id2term: Mutex<Option<GHashMap<usize, Term>>>,
+}
+
+#[cfg(feature = "sync")]
+impl Default for SyncTermManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "sync")]
+impl SyncTermManager {
+    pub fn new() -> Self {
+        Self {
+            avl_vid: AtomicUsize::new(0),
+            avl_tid: AtomicUsize::new(0),
+            shards: (0..NUM_SHARDS).map(|_| Mutex::default()).collect(),
+            id2term: Mutex::new(None),
+        }
+    }
+
+    #[inline]
+    fn shard(&self, ty: &TermType) -> &Mutex<GHashMap<TermType, Term>> {
+        let mut hasher = DefaultHasher::new();
+        ty.hash(&mut hasher);
+        &self.shards[hasher.finish() as usize % self.shards.len()]
+    }
+
+    pub fn new_term(&self, ty: TermType, sort: Sort) -> Term {
+        let mut shard = self.shard(&ty).lock().unwrap();
+        if let Some(term) = shard.get(&ty) {
+            return term.clone();
         }
-        self.map
-            .retain(|_, term| live.contains(&term.inner.as_ptr()));
-        if let Some(id2term) = &mut self.id2term {
+        let id = self.avl_tid.fetch_add(1, Ordering::Relaxed);
+        let term = Term {
+            inner: TermPtr::new(TermInner {
+                id,
+                sort,
+                ty: ty.clone(),
+            }),
+        };
+        shard.insert(ty, term.clone());
+        if let Some(id2term) = self.id2term.lock().unwrap().as_mut() {
+            id2term.insert(id, term.clone());
+        }
+        term
+    }
+
+    #[inline]
+    pub fn new_var(&self, sort: Sort) -> Term {
+        let id = self.avl_vid.fetch_add(1, Ordering::Relaxed);
+        self.new_term(TermType::Var(id), sort)
+    }
+
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|s| s.lock().unwrap().len()).sum()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn enable_id_map(&self) {
+        let shards: Vec<_> = self.shards.iter().map(|s| s.lock().unwrap()).collect();
+        let mut id2term = self.id2term.lock().unwrap();
+        if id2term.is_none() {
+            let maps: Vec<_> = shards.iter().map(|s| &**s).collect();
+            *id2term = Some(id_map(&maps));
+        }
+    }
+
+    #[inline]
+    pub fn disable_id_map(&self) {
+        *self.id2term.lock().unwrap() = None;
+    }
+
+    #[inline]
+    pub fn get_term_by_id(&self, id: usize) -> Option<Term> {
+        self.id2term.lock().unwrap().as_ref()?.get(&id).cloned()
+    }
+
+    /// Remove the terms only held by the manager. The other threads can
+    /// keep working on their terms meanwhile, but not make new ones.
+    pub fn garbage_collect(&self) {
+        let mut shards: Vec<_> = self.shards.iter().map(|s| s.lock().unwrap()).collect();
+        let mut id2term = self.id2term.lock().unwrap();
+        let maps: Vec<_> = shards.iter().map(|s| &**s).collect();
+        let before: usize = maps.iter().map(|m| m.len()).sum();
+        let live = live_terms(&maps, id2term.as_ref());
+        for shard in shards.iter_mut() {
+            retain_live(shard, None, &live);
+        }
+        if let Some(id2term) = id2term.as_mut() {
             id2term.retain(|_, term| live.contains(&term.inner.as_ptr()));
             id2term.shrink_to_fit();
         }
-        let after = self.map.len();
-        self.map.shrink_to_fit();
+        let after: usize = shards.iter().map(|s| s.len()).sum();
         debug!(
             "term GC cleared {} terms ({} -> {})",
             before - after,
@@ -156,24 +359,52 @@ impl TermManager {
             after
         );
     }
+
+    fn to_snapshot(&self) -> TermManagerSnapshot {
+        let shards: Vec<_> = self.shards.iter().map(|s| s.lock().unwrap()).collect();
+        TermManagerSnapshot::new(
+            self.avl_vid.load(Ordering::Relaxed),
+            self.avl_tid.load(Ordering::Relaxed),
+            shards.iter().flat_map(|s| s.values()),
+        )
+    }
 }
 
 thread_local! {
-    static TERM_MANAGER: UnsafeCell<TermManager> = UnsafeCell::new(TermManager::new());
+    static TERM_MANAGER: RefCell<TermManager> = RefCell::new(TermManager::new());
 }
 
+/// Run `f` on the TermManager of this thread. New terms can not be made in
+/// `f`, as the manager is borrowed.
 #[inline]
-pub fn term_mgr() -> &'static mut TermManager {
-    TERM_MANAGER.with(|m| unsafe { &mut *m.get() })
+pub fn with_term_mgr<R>(f: impl FnOnce(&mut TermManager) -> R) -> R {
+    TERM_MANAGER.with_borrow_mut(f)
+}
+
+/// Set the TermManager of this thread, returns the previous one.
+pub fn set_term_mgr(manager: TermManager) -> TermManager {
+    with_term_mgr(|m| replace(m, manager))
 }
 
-/// Set global TermManager
-pub fn set_term_mgr(manager: TermManager) {
-    *term_mgr() = manager;
+/// Make `manager` the TermManager of this thread while running `f`, so the
+/// terms made in `f` belong to it. Terms of the other managers must not be
+/// mixed with them.
+pub fn scoped_term_mgr<R>(manager: &mut TermManager, f: impl FnOnce() -> R) -> R {
+    struct Restore<'a>(&'a mut TermManager);
+
+    impl Drop for Restore<'_> {
+        fn drop(&mut self) {
+            with_term_mgr(|m| swap(m, self.0));
+        }
+    }
+
+    with_term_mgr(|m| swap(m, manager));
+    let _restore = Restore(manager);
+    f()
 }
 
 pub fn term_gc() {
-    term_mgr().garbage_collect();
+    with_term_mgr(|m| m.garbage_collect());
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -190,24 +421,22 @@ struct SerializedTerm {
     ty: SerializedTermType,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
-enum SerializedTermType {
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(super) enum SerializedTermType {
     Const(BitVec),
     Var(usize),
     Op(SerializedOpTerm),
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
-struct SerializedOpTerm {
-    op: String,
-    terms: Vec<usize>,
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(super) struct SerializedOpTerm {
+    pub(super) op: String,
+    pub(super) terms: Vec<usize>,
 }
 
-impl TermManager {
-    fn to_snapshot(&self) -> TermManagerSnapshot {
-        let mut terms: Vec<_> = self
-            .map
-            .values()
+impl TermManagerSnapshot {
+    fn new<'a>(avl_vid: usize, avl_tid: usize, terms: impl Iterator<Item = &'a Term>) -> Self {
+        let mut terms: Vec<_> = terms
             .map(|term| SerializedTerm {
                 id: term.id(),
                 sort: term.sort(),
@@ -215,12 +444,22 @@ impl TermManager {
             })
             .collect();
         terms.sort_by_key(|term| term.id);
-        TermManagerSnapshot {
-            avl_vid: self.avl_vid,
-            avl_tid: self.avl_tid,
+        Self {
+            avl_vid,
+            avl_tid,
             terms,
         }
     }
+}
+
+impl TermManager {
+    fn to_snapshot(&self) -> TermManagerSnapshot {
+        #[cfg(feature = "sync")]
+        if let Some(shared) = &self.shared {
+            return shared.to_snapshot();
+        }
+        TermManagerSnapshot::new(self.avl_vid, self.avl_tid, self.map.values())
+    }
 
     fn from_snapshot(snapshot: TermManagerSnapshot) -> Self {
         let mut manager = Self::new();
@@ -230,7 +469,7 @@ impl TermManager {
             let id = term.id;
             let ty = term.ty.into_term_type(&id2term);
             let new_term = Term {
-                inner: Grc::new(TermInner {
+                inner: TermPtr::new(TermInner {
                     id,
                     sort: term.sort,
                     ty: ty.clone(),
@@ -307,7 +546,7 @@ mod tests {
         let expr = &x + &y;
         let expr_id = expr.id();
 
-        let snapshot = term_mgr().to_snapshot();
+        let snapshot = with_term_mgr(|m| m.to_snapshot());
         let manager = TermManager::from_snapshot(snapshot);
 
         drop(expr);
@@ -315,8 +554,8 @@ mod tests {
         drop(x);
         set_term_mgr(manager);
 
-        term_mgr().enable_id_map();
-        let expr = term_mgr().get_term_by_id(expr_id).unwrap();
+        with_term_mgr(|m| m.enable_id_map());
+        let expr = with_term_mgr(|m| m.get_term_by_id(expr_id)).unwrap();
         assert_eq!(expr.id(), expr_id);
 
         let z = Term::new_var(Sort::Bv(4));
@@ -325,10 +564,10 @@ mod tests {
 
     #[test]
     fn optional_id_map_tracks_new_terms() {
-        term_mgr().enable_id_map();
+        with_term_mgr(|m| m.enable_id_map());
         let x = Term::new_var(Sort::bool());
-        assert_eq!(term_mgr().get_term_by_id(x.id()).unwrap(), x);
-        term_mgr().disable_id_map();
+        assert_eq!(with_term_mgr(|m| m.get_term_by_id(x.id())).unwrap(), x);
+        with_term_mgr(|m| m.disable_id_map());
     }
 
     #[test]
@@ -350,7 +589,7 @@ mod tests {
     fn term_gc_collects_dead_dag_with_id_map_enabled() {
         set_term_mgr(TermManager::new());
 
-        term_mgr().enable_id_map();
+        with_term_mgr(|m| m.enable_id_map());
         let c = Term::bool_const(true);
         let x = Term::new_var(Sort::bool());
         let expr = &c & &x;
@@ -361,7 +600,58 @@ mod tests {
 
         term_gc();
 
-        assert!(term_mgr().is_empty());
-        assert!(term_mgr().get_term_by_id(expr_id).is_none());
+        assert!(with_term_mgr(|m| m.is_empty()));
+        assert!(with_term_mgr(|m| m.get_term_by_id(expr_id)).is_none());
+    }
+
+    #[test]
+    fn scoped_term_manager() {
+        let x = Term::new_var(Sort::bool());
+        let mut scoped = TermManager::new();
+        let y = scoped_term_mgr(&mut scoped, || {
+            let y = Term::new_var(Sort::bool());
+            assert_eq!(with_term_mgr(|m| m.len()), 1);
+            y
+        });
+        assert_eq!(scoped.len(), 1);
+        assert_eq!(y.id(), 0);
+        assert_ne!(y, x);
+        assert_eq!(Term::new_var(Sort::bool()).id(), x.id() + 1);
+    }
+
+    #[cfg(feature = "sync")]
+    #[test]
+    fn sync_term_manager() {
+        use crate::fol::bitblast::bitblast_terms;
+        use std::thread;
+
+        let shared = Arc::new(SyncTermManager::new());
+        set_term_mgr(TermManager::shared(shared.clone()));
+        let x = Term::new_var(Sort::Bv(4));
+        let y = Term::new_var(Sort::Bv(4));
+        let expr = &x + &y;
+        let mut vars = GHashMap::new();
+        bitblast_terms([&x, &y], &mut vars).for_each(drop);
+        let blasted: Vec<_> = (0..4)
+            .map(|_| {
+                let (shared, x, y, mut map) = (shared.clone(), x.clone(), y.clone(), vars.clone());
+                thread::spawn(move || {
+                    set_term_mgr(TermManager::shared(shared));
+                    let expr = &x + &y;
+                    let bits = bitblast_terms([&expr], &mut map).next().unwrap();
+                    (expr, bits)
+                })
+            })
+            .map(|h| h.join().unwrap())
+            .collect();
+        for (e, bits) in blasted.iter() {
+            assert_eq!(e, &expr);
+            assert_eq!(bits, &blasted[0].1);
+        }
+        assert_eq!(with_term_mgr(|m| m.len()), shared.len());
+
+        drop((x, y, expr, vars, blasted));
+        term_gc();
+        assert!(shared.is_empty());
     }
 }
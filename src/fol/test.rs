@@ -1,8 +1,8 @@
 use super::op::FolOp;
 use super::simplify::SimplifyCtx;
-use super::{Sort, Term, Value};
-use crate::LboolVec;
+use super::{Sort, Term, TermGraph, TermPostOrder, TermRewriter, TermSymbol, TermVec, Value};
 use crate::OptLevel;
+use crate::{DagCnf, LboolVec, VarVMap};
 use giputils::bitvec::BitVec;
 use giputils::hash::GHashMap;
 
@@ -632,3 +632,156 @@ fn test_simplify_eq_and_const_contradiction() {
         Term::bool_const(false)
     );
 }
+
+#[test]
+fn test_substitute() {
+    let x = Term::new_var(Sort::Bv(8));
+    let y = Term::new_var(Sort::Bv(8));
+    let t = (&x + &y) & &x;
+    let map = GHashMap::from_iter([(x.clone(), y.clone()), (y.clone(), x.clone())]);
+    assert_eq!(t.substitute(&map), (&y + &x) & &y);
+    let ts = TermVec::from([t.clone(), &x - &y]).substitute(&map);
+    assert_eq!(ts[1], &y - &x);
+    assert_eq!(t.replace(&x, &y), (&y + &y) & &y);
+
+    // shared subterms are visited once
+    let mut d = x.clone();
+    for _ in 0..200 {
+        d = &d + &d;
+    }
+    let z = Term::new_var(Sort::Bv(8));
+    let mut e = z.clone();
+    for _ in 0..200 {
+        e = &e + &e;
+    }
+    assert_eq!(d.replace(&x, &z), e);
+
+    let mut d = x.clone();
+    for _ in 0..1000 {
+        d = &d + &y;
+    }
+    let map = GHashMap::from_iter([(y.clone(), x.clone())]);
+    let mut e = x.clone();
+    for _ in 0..1000 {
+        e = &e + &x;
+    }
+    assert_eq!(d.substitute(&map), e);
+}
+
+#[test]
+fn test_deep_term() {
+    const DEPTH: usize = 100000;
+    let x = Term::new_var(Sort::bool());
+    let y = Term::new_var(Sort::bool());
+    let mut t = x.clone();
+    for i in 0..DEPTH {
+        t = if i % 2 == 0 { &x } else { &y }.ite(!&t, &x ^ &y);
+    }
+    let mut val = GHashMap::new();
+    val.insert(x.clone(), bv_val("0"));
+    val.insert(y.clone(), bv_val("1"));
+    let v = t.simulate(&mut val);
+
+    let s = t.simplify(&mut GHashMap::new());
+    let mut val = GHashMap::new();
+    val.insert(x.clone(), bv_val("0"));
+    val.insert(y.clone(), bv_val("1"));
+    assert_eq!(s.simulate(&mut val).as_bv(), v.as_bv());
+
+    let b = t.bitblast(&mut GHashMap::new());
+    assert_eq!(b.len(), 1);
+    let mut dc = DagCnf::new();
+    let l = b[0].cnf_encode(&mut dc, &mut GHashMap::new());
+    let mut mdc = DagCnf::new();
+    let mut map = VarVMap::new();
+    mdc.migrate(&dc, l.var(), &mut map);
+    assert!(map.get(&l.var()).is_some());
+    assert!(*mdc.max_var() as usize > DEPTH);
+}
+
+#[test]
+fn test_visit() {
+    let x = Term::new_var(Sort::Bv(4));
+    let y = Term::new_var(Sort::Bv(4));
+    let s = &x + &y;
+    let t = &s & &(&s ^ &x);
+    let pre: Vec<Term> = t.pre_order().collect();
+    assert_eq!(pre.len(), 5);
+    assert_eq!(pre[0], t);
+    let post: Vec<Term> = t.post_order().collect();
+    assert_eq!(post.len(), 5);
+    assert_eq!(post[4], t);
+    for (i, p) in post.iter().enumerate() {
+        for c in p.try_op().into_iter().flat_map(|o| o.terms.iter()) {
+            assert!(post[..i].contains(c));
+        }
+    }
+    let roots: Vec<Term> = TermPostOrder::new([&s, &t]).collect();
+    assert_eq!(roots.len(), 5);
+    assert_eq!(roots[2], s);
+
+    let depth = t.fold(|_, ds: &[usize]| ds.iter().max().map_or(0, |d| d + 1));
+    assert_eq!(depth, 3);
+
+    struct AndToOr;
+    impl TermRewriter for AndToOr {
+        fn rewrite(&mut self, term: &Term, terms: &[Term]) -> Term {
+            match term.try_op() {
+                Some(o) if o.op == FolOp::And => Term::new_op(FolOp::Or, terms),
+                Some(o) => Term::new_op(o.op, terms),
+                None => term.clone(),
+            }
+        }
+    }
+    assert_eq!(t.rewrite(&mut AndToOr), &s | &(&s ^ &x));
+    let mut xy = |t: &Term| (*t == x).then(|| y.clone());
+    assert_eq!(t.rewrite(&mut xy), t.replace(&x, &y));
+}
+
+#[test]
+fn test_term_graph() {
+    let x = Term::new_var(Sort::Bv(4));
+    let y = Term::new_var(Sort::Bv(4));
+    let s = &x + &y;
+    let t = s.op1(FolOp::Ult, &x);
+    let mut symbols = TermSymbol::new();
+    symbols.add_symbol(&x, "x");
+    symbols.add_symbol(&s, "s");
+    let graph = TermGraph::export([&t, &s], &symbols);
+    assert_eq!(graph.len(), 4);
+
+    let back = std::thread::spawn(move || {
+        let (roots, symbols) = graph.import();
+        assert_eq!(roots.len(), 2);
+        let x = symbols.term_of_sym("x").unwrap();
+        assert_eq!(symbols.term_of_sym("s").unwrap(), roots[1]);
+        let neg = !&roots[0];
+        assert!(neg.is_op() && x.is_var());
+        (graph, TermGraph::export([neg], &symbols))
+    });
+    let (graph, neg) = back.join().unwrap();
+    let (roots, isymbols) = graph.import();
+    assert_eq!(TermGraph::export(&roots, &isymbols), graph);
+    let (neg, _) = neg.import();
+    assert_eq!(neg[0].try_op().unwrap().op, FolOp::Not);
+    assert_eq!(neg[0].try_op().unwrap().terms[0].sort(), Sort::bool());
+}
+
+#[test]
+fn test_term_graph_normalise() {
+    let x = Term::new_var(Sort::Bv(4));
+    let c = Term::bv_const(BitVec::from(&[true, false, true, false]));
+    let s = &x + &c;
+    let mut symbols = TermSymbol::new();
+    symbols.add_symbol(&x, "x");
+    let graph = TermGraph::export([&s], &symbols);
+    std::thread::spawn(move || {
+        let c = Term::bv_const(BitVec::from(&[true, false, true, false]));
+        let (roots, symbols) = graph.import();
+        let x = symbols.term_of_sym("x").unwrap();
+        assert!(c.id() < x.id());
+        assert_eq!(roots[0], &x + &c);
+    })
+    .join()
+    .unwrap();
+}
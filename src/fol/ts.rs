@@ -0,0 +1,341 @@
+use super::{ArrayValue, Sort, Term, TermSymbol, TermVec, Value};
+use crate::{
+    Lbool, LboolVec, Lit, Var,
+    ts::{Trace, TransitionSystem},
+};
+use giputils::hash::GHashMap;
+
+/// Word-level sequential model: states with init and next terms, inputs,
+/// constraints and bad properties, all as `Term`s over the state and input
+/// vars.
+#[derive(Clone, Debug, Default)]
+pub struct WordTransitionSystem {
+    pub input: TermVec,
+    pub latch: TermVec,
+    /// Initial value of the initialized states.
+    pub init: GHashMap<Term, Term>,
+    pub next: GHashMap<Term, Term>,
+    pub constraint: TermVec,
+    pub bad: TermVec,
+    pub symbols: TermSymbol,
+}
+
+impl WordTransitionSystem {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    pub fn new_input(&mut self, sort: Sort) -> Term {
+        let t = Term::new_var(sort);
+        self.input.push(t.clone());
+        t
+    }
+
+    /// New state with the given initial value, its next term is set with
+    /// `set_next`. A state without next term takes a free value in every
+    /// step after the first.
+    #[inline]
+    pub fn new_latch(&mut self, sort: Sort, init: Option<Term>) -> Term {
+        let t = Term::new_var(sort);
+        self.latch.push(t.clone());
+        if let Some(i) = init {
+            assert_eq!(i.sort(), sort);
+            self.init.insert(t.clone(), i);
+        }
+        t
+    }
+
+    #[inline]
+    pub fn set_next(&mut self, latch: &Term, next: Term) {
+        assert_eq!(latch.sort(), next.sort());
+        self.next.insert(latch.clone(), next);
+    }
+
+    /// Lower to a bit-level `TransitionSystem`. Every input and state bit
+    /// becomes an input or latch var, mapped back to its term and bit in the
+    /// returned map. The next bits of a state without next term are fresh
+    /// inputs. Initial bits that are not constant become constraints on the
+    /// first frame, which is marked by an extra latch.
+    pub fn bitblast(&self) -> (TransitionSystem, WordBitMap) {
+        let mut ts = TransitionSystem::new();
+        let mut bmap = GHashMap::new();
+        let mut emap: GHashMap<Term, Lit> = GHashMap::new();
+        let mut bits = GHashMap::new();
+        let add_symbols = |ts: &mut TransitionSystem, t: &Term, v: Var, k: usize| {
+            for s in self.symbols.get(t).into_iter().flatten() {
+                ts.symbols.insert(v, s.clone(), k);
+            }
+        };
+        for i in self.input.iter() {
+            for (k, b) in i.bitblast(&mut bmap).iter().enumerate() {
+                let v = ts.new_input();
+                emap.insert(b.clone(), v.lit());
+                bits.insert(v, (i.clone(), k));
+                add_symbols(&mut ts, i, v, k);
+            }
+        }
+        let mut latch = Vec::new();
+        let mut free_init = Vec::new();
+        for l in self.latch.iter() {
+            let init = self.init.get(l).map(|i| i.bitblast(&mut bmap));
+            let mut lv = Vec::new();
+            for (k, b) in l.bitblast(&mut bmap).iter().enumerate() {
+                let init = init.as_ref().and_then(|i| i[k].try_bool_const());
+                let v = ts.new_latch(init);
+                emap.insert(b.clone(), v.lit());
+                bits.insert(v, (l.clone(), k));
+                add_symbols(&mut ts, l, v, k);
+                lv.push(v);
+            }
+            if let Some(i) = init.filter(|i| i.iter().any(|b| b.try_bool_const().is_none())) {
+                free_init.push((lv.clone(), i));
+            }
+            latch.push(lv);
+        }
+        for (l, lv) in self.latch.iter().zip(latch) {
+            let Some(next) = self.next.get(l) else {
+                for v in lv {
+                    let n = ts.new_input().lit();
+                    ts.set_next(v, n);
+                }
+                continue;
+            };
+            let next = next.bitblast(&mut bmap);
+            for (v, n) in lv.into_iter().zip(next.iter()) {
+                let n = n.cnf_encode(&mut ts.rel, &mut emap);
+                ts.set_next(v, n);
+            }
+        }
+        if !free_init.is_empty() {
+            let first = ts.new_latch(Some(true));
+            ts.set_next(first, Lit::constant(false));
+            for (lv, init) in free_init {
+                for (v, i) in lv.into_iter().zip(init.iter()) {
+                    if i.try_bool_const().is_some() {
+                        continue;
+                    }
+                    let i = i.cnf_encode(&mut ts.rel, &mut emap);
+                    let eq = ts.rel.new_xnor(v.lit(), i);
+                    let c = ts.rel.new_or([!first.lit(), eq]);
+                    ts.constraint.push(c);
+                }
+            }
+        }
+        let mut encode = |ts: &mut TransitionSystem, t: &Term| {
+            t.bitblast(&mut bmap)
+                .item()
+                .cnf_encode(&mut ts.rel, &mut emap)
+        };
+        for c in self.constraint.iter() {
+            let c = encode(&mut ts, c);
+            ts.constraint.push(c);
+        }
+        for b in self.bad.iter() {
+            let b = encode(&mut ts, b);
+            ts.bad.push(b);
+        }
+        (ts, WordBitMap { bits })
+    }
+}
+
+/// Map from the input and latch vars of a bitblasted system back to the
+/// word-level terms and bits.
+#[derive(Clone, Debug, Default)]
+pub struct WordBitMap {
+    pub bits: GHashMap<Var, (Term, usize)>,
+}
+
+impl WordBitMap {
+    /// Word-level values of the terms with an assigned bit in `lits`, the
+    /// other bits are X.
+    pub fn lift(&self, lits: &[Lit]) -> GHashMap<Term, Value> {
+        let mut res: GHashMap<Term, Value> = GHashMap::new();
+        for l in lits {
+            let Some((t, k)) = self.bits.get(&l.var()) else {
+                continue;
+            };
+            let val = res
+                .entry(t.clone())
+                .or_insert_with(|| Value::default_from(t.sort()));
+            match val {
+                Value::Bv(bv) => bv.set_bool(*k, l.polarity()),
+                Value::Array(a) => lift_array_bit(a, *k, l.polarity()),
+            }
+        }
+        res
+    }
+
+    /// Word-level values of the inputs and states of every frame.
+    pub fn lift_trace(&self, trace: &Trace) -> Vec<GHashMap<Term, Value>> {
+        (0..trace.len())
+            .map(|k| {
+                let lits: Vec<Lit> = trace.state[k]
+                    .iter()
+                    .chain(trace.input[k].iter())
+                    .copied()
+                    .collect();
+                self.lift(&lits)
+            })
+            .collect()
+    }
+}
+
+/// Bits of an array are laid out element by element.
+fn lift_array_bit(a: &mut ArrayValue, k: usize, v: bool) {
+    let (_, e) = a.sort().array();
+    a.entry(k / e)
+        .or_insert_with(|| LboolVec::from_elem(Lbool::NONE, e))
+        .set_bool(k % e, v);
+}
+
+/// Time-frame expansion of a `WordTransitionSystem`. Frame 0 has fresh
+/// state vars, the states of frame `k + 1` are the next terms of frame `k`;
+/// every frame has fresh input vars.
+#[derive(Clone, Debug)]
+pub struct WordUnroll {
+    pub ts: WordTransitionSystem,
+    frames: Vec<GHashMap<Term, Term>>,
+    cache: Vec<GHashMap<Term, Term>>,
+}
+
+impl WordUnroll {
+    pub fn new(ts: WordTransitionSystem) -> Self {
+        Self {
+            ts,
+            frames: Vec::new(),
+            cache: Vec::new(),
+        }
+    }
+
+    #[inline]
+    pub fn num_frame(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn unroll(&mut self) {
+        let k = self.frames.len();
+        let mut map = GHashMap::new();
+        for i in self.ts.input.iter() {
+            map.insert(i.clone(), Term::new_var(i.sort()));
+        }
+        for l in self.ts.latch.clone() {
+            let t = match self.ts.next.get(&l).cloned() {
+                Some(next) if k > 0 => self.term(&next, k - 1),
+                _ => Term::new_var(l.sort()),
+            };
+            map.insert(l, t);
+        }
+        self.frames.push(map);
+        self.cache.push(GHashMap::new());
+    }
+
+    #[inline]
+    pub fn unroll_to(&mut self, k: usize) {
+        while self.frames.len() <= k {
+            self.unroll();
+        }
+    }
+
+    /// Copy of `t` at frame `k`. Results are memoised per frame.
+    pub fn term(&mut self, t: &Term, k: usize) -> Term {
+        t.cached_substitute(&self.frames[k], &mut self.cache[k])
+    }
+
+    /// The initial states as a constraint on frame 0.
+    pub fn init(&mut self) -> Term {
+        let mut init = Vec::new();
+        for l in self.ts.latch.clone() {
+            if let Some(i) = self.ts.init.get(&l).cloned() {
+                init.push(self.term(&l, 0).teq(self.term(&i, 0)));
+            }
+        }
+        Term::new_ands(init)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{WordTransitionSystem, WordUnroll};
+    use crate::{
+        LboolVec,
+        fol::{Sort, Term, Value},
+        satif::test::DpllSolver,
+        ts::{Bmc, BmcResult},
+    };
+    use giputils::bitvec::BitVec;
+
+    #[test]
+    fn test_word_ts() {
+        let mut ts = WordTransitionSystem::new();
+        let en = ts.new_input(Sort::bool());
+        let c = ts.new_latch(Sort::Bv(4), Some(Term::bv_const(BitVec::zero(4))));
+        let one = c.mk_bv_const_one();
+        ts.set_next(&c, en.ite(&c + &one, &c));
+        ts.bad
+            .push(c.teq(Term::bv_const(BitVec::from(&[true, false, true, false]))));
+        ts.symbols.add_symbol(&c, "c");
+
+        let mut u = WordUnroll::new(ts.clone());
+        u.unroll_to(2);
+        let (c0, en0) = (u.term(&c, 0), u.term(&en, 0));
+        assert_eq!(u.term(&c, 1), en0.ite(&c0 + &one, &c0));
+        let (c1, en1) = (u.term(&c, 1), u.term(&en, 1));
+        assert_eq!(u.term(&c, 2), en1.ite(&c1 + &one, &c1));
+        assert_ne!(u.term(&en, 1), en0);
+
+        let (bts, map) = ts.bitblast();
+        assert_eq!(bts.input.len(), 1);
+        assert_eq!(bts.init.len(), 4);
+        assert_eq!(bts.symbols.get(bts.latch[2]), vec![("c".to_string(), 2)]);
+        let BmcResult::Cex(trace) = Bmc::new(&bts, DpllSolver::new()).check(6) else {
+            panic!()
+        };
+        assert_eq!(trace.len(), 6);
+        let vals = map.lift_trace(&trace);
+        let Value::Bv(last) = &vals[5][&c] else {
+            panic!()
+        };
+        assert_eq!(last, &LboolVec::from("0101"));
+    }
+
+    #[test]
+    fn test_free_state() {
+        let mut ts = WordTransitionSystem::new();
+        let i = ts.new_input(Sort::Bv(2));
+        let s = ts.new_latch(Sort::Bv(2), Some(i.clone()));
+        ts.bad
+            .push(s.teq(Term::bv_const(BitVec::from(&[true, true]))));
+        let (bts, _) = ts.bitblast();
+        assert_eq!(bts.latch.len(), 3);
+        assert_eq!(bts.init.len(), 1);
+        assert_eq!(bts.constraint.len(), 2);
+        assert_eq!(bts.input.len(), 4);
+        assert!(
+            bts.latch[..2]
+                .iter()
+                .all(|l| bts.input.contains(&bts.next[l].var()))
+        );
+
+        let mut u = WordUnroll::new(ts);
+        u.unroll_to(1);
+        assert_ne!(u.term(&s, 1), u.term(&s, 0));
+    }
+
+    #[test]
+    fn test_init_constraint() {
+        let mut ts = WordTransitionSystem::new();
+        let zero = Term::bv_const(BitVec::zero(2));
+        let a = ts.new_latch(Sort::Bv(2), Some(zero.clone()));
+        let s = ts.new_latch(Sort::Bv(2), Some(a.clone()));
+        ts.set_next(&a, a.mk_bv_const_one());
+        ts.set_next(&s, s.clone());
+        ts.bad.push(!s.teq(&zero));
+        let (bts, _) = ts.bitblast();
+        assert!(matches!(
+            Bmc::new(&bts, DpllSolver::new()).check(3),
+            BmcResult::NoCex(3)
+        ));
+    }
+}
@@ -0,0 +1,202 @@
+use super::Term;
+use giputils::hash::{GHashMap, GHashSet};
+
+/// Pre-order iterator over the unique terms reachable from some roots,
+/// parents before children, left to right.
+pub struct TermPreOrder {
+    stack: Vec<Term>,
+    visited: GHashSet<Term>,
+}
+
+impl TermPreOrder {
+    pub fn new(roots: impl IntoIterator<Item = impl AsRef<Term>>) -> Self {
+        let mut stack: Vec<Term> = roots.into_iter().map(|t| t.as_ref().clone()).collect();
+        stack.reverse();
+        Self {
+            stack,
+            visited: GHashSet::new(),
+        }
+    }
+}
+
+impl Iterator for TermPreOrder {
+    type Item = Term;
+
+    fn next(&mut self) -> Option<Term> {
+        while let Some(t) = self.stack.pop() {
+            if !self.visited.insert(t.clone()) {
+                continue;
+            }
+            if let Some(op_term) = t.try_op() {
+                for s in op_term.terms.iter().rev() {
+                    if !self.visited.contains(s) {
+                        self.stack.push(s.clone());
+                    }
+                }
+            }
+            return Some(t);
+        }
+        None
+    }
+}
+
+/// Post-order iterator over the unique terms reachable from some roots,
+/// children before parents, left to right.
+pub struct TermPostOrder {
+    stack: Vec<(Term, bool)>,
+    visited: GHashSet<Term>,
+}
+
+impl TermPostOrder {
+    pub fn new(roots: impl IntoIterator<Item = impl AsRef<Term>>) -> Self {
+        let mut stack: Vec<_> = roots
+            .into_iter()
+            .map(|t| (t.as_ref().clone(), false))
+            .collect();
+        stack.reverse();
+        Self {
+            stack,
+            visited: GHashSet::new(),
+        }
+    }
+}
+
+impl Iterator for TermPostOrder {
+    type Item = Term;
+
+    fn next(&mut self) -> Option<Term> {
+        while let Some((t, expanded)) = self.stack.pop() {
+            if expanded {
+                return Some(t);
+            }
+            if !self.visited.insert(t.clone()) {
+                continue;
+            }
+            self.stack.push((t.clone(), true));
+            if let Some(op_term) = t.try_op() {
+                for s in op_term.terms.iter().rev() {
+                    if !self.visited.contains(s) {
+                        self.stack.push((s.clone(), false));
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Bottom-up rebuilding of terms, see `Term::rewrite`.
+pub trait TermRewriter {
+    /// Result for `term` without visiting its subterms, if any.
+    #[inline]
+    fn pre_rewrite(&mut self, _term: &Term) -> Option<Term> {
+        None
+    }
+
+    /// Result for `term`, given the results of its subterms.
+    #[inline]
+    fn rewrite(&mut self, term: &Term, terms: &[Term]) -> Term {
+        match term.try_op() {
+            Some(op_term) => Term::new_op(op_term.op, terms),
+            None => term.clone(),
+        }
+    }
+}
+
+impl<F: FnMut(&Term) -> Option<Term>> TermRewriter for F {
+    #[inline]
+    fn pre_rewrite(&mut self, term: &Term) -> Option<Term> {
+        self(term)
+    }
+}
+
+impl Term {
+    /// Post-order traversal of the subterms missing in `map` on an explicit
+    /// stack. A subterm gets `pre(ctx, t)` if it is Some, otherwise
+    /// `post(ctx, t, vals)` with the values of its children.
+    fn fold_with<C: ?Sized, V: Clone>(
+        &self,
+        map: &mut GHashMap<Term, V>,
+        ctx: &mut C,
+        pre: impl Fn(&mut C, &Term) -> Option<V>,
+        post: impl Fn(&mut C, &Term, &[V]) -> V,
+    ) -> V {
+        let mut stack = vec![(self.clone(), false)];
+        while let Some((t, expanded)) = stack.pop() {
+            if map.contains_key(&t) {
+                continue;
+            }
+            if expanded {
+                let vals: Vec<V> = t
+                    .try_op()
+                    .map(|op_term| op_term.terms.iter().map(|s| map[s].clone()).collect())
+                    .unwrap_or_default();
+                let v = post(ctx, &t, &vals);
+                map.insert(t, v);
+                continue;
+            }
+            if let Some(v) = pre(ctx, &t) {
+                map.insert(t, v);
+                continue;
+            }
+            stack.push((t.clone(), true));
+            if let Some(op_term) = t.try_op() {
+                for s in op_term.terms.iter().rev() {
+                    if !map.contains_key(s) {
+                        stack.push((s.clone(), false));
+                    }
+                }
+            }
+        }
+        map[self].clone()
+    }
+
+    /// Compute `f(t, vals)` for every subterm `t` missing in `map`, where
+    /// `vals` are the values of the children of `t`, and memoise it in `map`.
+    /// The subterms are visited in post-order without recursion.
+    #[inline]
+    pub fn cached_fold<V: Clone>(
+        &self,
+        map: &mut GHashMap<Term, V>,
+        mut f: impl FnMut(&Term, &[V]) -> V,
+    ) -> V {
+        self.fold_with(map, &mut f, |_, _| None, |f, t, vals| f(t, vals))
+    }
+
+    #[inline]
+    pub fn fold<V: Clone>(&self, f: impl FnMut(&Term, &[V]) -> V) -> V {
+        self.cached_fold(&mut GHashMap::new(), f)
+    }
+
+    /// Rebuild `self` bottom-up with `r`, with the results memoised in `map`.
+    #[inline]
+    pub fn cached_rewrite(
+        &self,
+        r: &mut (impl TermRewriter + ?Sized),
+        map: &mut GHashMap<Term, Term>,
+    ) -> Term {
+        self.fold_with(
+            map,
+            r,
+            |r, t| r.pre_rewrite(t),
+            |r, t, terms| r.rewrite(t, terms),
+        )
+    }
+
+    #[inline]
+    pub fn rewrite(&self, r: &mut (impl TermRewriter + ?Sized)) -> Term {
+        self.cached_rewrite(r, &mut GHashMap::new())
+    }
+
+    /// Unique subterms of `self`, parents first.
+    #[inline]
+    pub fn pre_order(&self) -> TermPreOrder {
+        TermPreOrder::new([self])
+    }
+
+    /// Unique subterms of `self`, children first.
+    #[inline]
+    pub fn post_order(&self) -> TermPostOrder {
+        TermPostOrder::new([self])
+    }
+}
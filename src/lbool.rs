@@ -145,6 +145,13 @@ impl LboolVec {
         }
     }
 
+    /// Build from the value bits and the mask of defined bits.
+    #[inline]
+    pub fn from_parts(v: BitVec, m: BitVec) -> Self {
+        assert_eq!(v.len(), m.len());
+        Self { v, m }
+    }
+
     #[inline]
     pub fn len(&self) -> usize {
         self.v.len()
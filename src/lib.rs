@@ -9,7 +9,9 @@ mod litordvec;
 mod litvec;
 mod litvvec;
 mod occur;
+mod reconstruct;
 pub mod satif;
+pub mod ts;
 mod utils;
 
 pub use assign::*;
@@ -20,6 +22,7 @@ pub use lbool::*;
 pub use litordvec::*;
 pub use litvec::*;
 pub use litvvec::*;
+pub use reconstruct::*;
 use serde::{Deserialize, Serialize};
 pub use utils::*;
 
@@ -0,0 +1,98 @@
+use crate::{Lit, LitVec, Var, VarAssign};
+
+#[derive(Clone, Debug)]
+enum ReconstructItem {
+    /// The var was fixed to the value of the literal.
+    Assign(Lit),
+    /// The var was eliminated by resolution on these clauses.
+    Eliminate(Var, Vec<LitVec>),
+    /// The var was substituted by an equivalent literal.
+    Equiv(Var, Lit),
+    /// The clause was removed, it is repaired by making the literal true.
+    Witness(Lit, LitVec),
+}
+
+/// Removals done by a simplifier, used to extend a model of the simplified
+/// formula to a model of the original one.
+#[derive(Clone, Debug, Default)]
+pub struct ReconstructStack {
+    stack: Vec<ReconstructItem>,
+}
+
+impl ReconstructStack {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.stack.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.stack.is_empty()
+    }
+
+    #[inline]
+    pub fn push_assign(&mut self, lit: Lit) {
+        self.stack.push(ReconstructItem::Assign(lit));
+    }
+
+    #[inline]
+    pub fn push_eliminate(&mut self, v: Var, cls: Vec<LitVec>) {
+        self.stack.push(ReconstructItem::Eliminate(v, cls));
+    }
+
+    #[inline]
+    pub fn push_equiv(&mut self, v: Var, lit: Lit) {
+        self.stack.push(ReconstructItem::Equiv(v, lit));
+    }
+
+    #[inline]
+    pub fn push_witness(&mut self, witness: Lit, cls: LitVec) {
+        self.stack.push(ReconstructItem::Witness(witness, cls));
+    }
+
+    /// Assign the removed vars, latest removal first. `assign` must be a full
+    /// model of the simplified formula, vars left unassigned in it are
+    /// treated as not satisfying any clause.
+    pub fn extend(&self, assign: &mut VarAssign) {
+        for item in self.stack.iter().rev() {
+            match item {
+                ReconstructItem::Assign(l) => {
+                    assign.reserve(l.var());
+                    assign.set(*l);
+                }
+                ReconstructItem::Eliminate(v, cls) => {
+                    assign.reserve(*v);
+                    let mut val = !v.lit();
+                    for c in cls.iter() {
+                        for l in c.iter() {
+                            assign.reserve(l.var());
+                        }
+                        let sat = c.iter().any(|&l| l.var() != *v && assign.v(l).is_true());
+                        if !sat {
+                            val = *c.iter().find(|l| l.var() == *v).unwrap();
+                            break;
+                        }
+                    }
+                    assign.set(val);
+                }
+                ReconstructItem::Equiv(v, l) => {
+                    assign.reserve((*v).max(l.var()));
+                    assign.set(v.lit().not_if(!assign.v(*l).is_true()));
+                }
+                ReconstructItem::Witness(w, cls) => {
+                    for l in cls.iter() {
+                        assign.reserve(l.var());
+                    }
+                    if !cls.iter().any(|&l| assign.v(l).is_true()) {
+                        assign.set(*w);
+                    }
+                }
+            }
+        }
+    }
+}
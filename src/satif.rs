@@ -88,3 +88,151 @@ pub trait Satif: Send {
         panic!("unsupport get_terminate_ctrl");
     }
 }
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::Satif;
+    use crate::{Lit, LitVec, Var};
+
+    /// Plain DPLL solver for testing code built on `Satif`. Failed
+    /// assumptions are minimized by deletion.
+    pub(crate) struct DpllSolver {
+        num_var: usize,
+        cls: Vec<LitVec>,
+        model: Vec<Option<bool>>,
+        core: Vec<Lit>,
+    }
+
+    impl DpllSolver {
+        pub(crate) fn new() -> Self {
+            Self {
+                num_var: 1,
+                cls: Vec::new(),
+                model: Vec::new(),
+                core: Vec::new(),
+            }
+        }
+
+        #[inline]
+        fn value(asgn: &[Option<bool>], l: Lit) -> Option<bool> {
+            asgn[l.var().0 as usize].map(|b| b == l.polarity())
+        }
+
+        fn propagate(&self, asgn: &mut [Option<bool>], trail: &mut Vec<Var>) -> bool {
+            loop {
+                let mut changed = false;
+                for c in self.cls.iter() {
+                    let mut unassigned = None;
+                    let mut num = 0;
+                    let mut sat = false;
+                    for &l in c.iter() {
+                        match Self::value(asgn, l) {
+                            Some(true) => {
+                                sat = true;
+                                break;
+                            }
+                            Some(false) => (),
+                            None => {
+                                num += 1;
+                                unassigned = Some(l);
+                            }
+                        }
+                    }
+                    if sat {
+                        continue;
+                    }
+                    if num == 0 {
+                        return false;
+                    }
+                    if num == 1 {
+                        let l = unassigned.unwrap();
+                        asgn[l.var().0 as usize] = Some(l.polarity());
+                        trail.push(l.var());
+                        changed = true;
+                    }
+                }
+                if !changed {
+                    return true;
+                }
+            }
+        }
+
+        fn dpll(&self, asgn: &mut [Option<bool>]) -> bool {
+            let mut trail = Vec::new();
+            if self.propagate(asgn, &mut trail) {
+                let Some(v) = asgn.iter().position(|a| a.is_none()) else {
+                    return true;
+                };
+                for b in [true, false] {
+                    asgn[v] = Some(b);
+                    if self.dpll(asgn) {
+                        return true;
+                    }
+                }
+                asgn[v] = None;
+            }
+            for v in trail {
+                asgn[v.0 as usize] = None;
+            }
+            false
+        }
+
+        fn solve_assumps(&self, assumps: &[Lit]) -> Option<Vec<Option<bool>>> {
+            let mut asgn = vec![None; self.num_var];
+            for &a in assumps {
+                if Self::value(&asgn, a) == Some(false) {
+                    return None;
+                }
+                asgn[a.var().0 as usize] = Some(a.polarity());
+            }
+            self.dpll(&mut asgn).then_some(asgn)
+        }
+    }
+
+    impl Satif for DpllSolver {
+        fn new_var(&mut self) -> Var {
+            self.num_var += 1;
+            Var::new(self.num_var - 1)
+        }
+
+        fn num_var(&self) -> usize {
+            self.num_var
+        }
+
+        fn add_clause(&mut self, clause: &[Lit]) {
+            for l in clause.iter() {
+                self.new_var_to(l.var());
+            }
+            self.cls.push(LitVec::from(clause));
+        }
+
+        fn solve(&mut self, assumps: &[Lit]) -> bool {
+            if let Some(model) = self.solve_assumps(assumps) {
+                self.model = model;
+                return true;
+            }
+            let mut core = assumps.to_vec();
+            for i in (0..core.len()).rev() {
+                let mut c = core.clone();
+                c.remove(i);
+                if self.solve_assumps(&c).is_none() {
+                    core = c;
+                }
+            }
+            self.core = core;
+            false
+        }
+
+        fn sat_value(&self, lit: Lit) -> Option<bool> {
+            self.model
+                .get(lit.var().0 as usize)
+                .copied()
+                .flatten()
+                .map(|b| b == lit.polarity())
+        }
+
+        fn unsat_has(&self, lit: Lit) -> bool {
+            self.core.contains(&lit)
+        }
+    }
+}
@@ -0,0 +1,580 @@
+use super::TransitionSystem;
+use crate::{
+    Lit, LitVec, Var, VarLMap,
+    dagcnf::gate::{GATE_TT_MAX_SUPPORT, Gate},
+};
+use giputils::hash::GHashSet;
+use std::{
+    fs::{File, read},
+    io::Write,
+    path::Path,
+};
+
+/// Symbols of the form `name[idx]` map to `(name, idx)`.
+fn parse_symbol(s: &str) -> (String, usize) {
+    if let Some(b) = s.strip_suffix(']')
+        && let Some((name, idx)) = b.rsplit_once('[')
+        && let Ok(idx) = idx.parse()
+    {
+        return (name.to_string(), idx);
+    }
+    (s.to_string(), 0)
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn line(&mut self) -> Option<&str> {
+        if self.pos >= self.data.len() {
+            return None;
+        }
+        let start = self.pos;
+        while self.pos < self.data.len() && self.data[self.pos] != b'\n' {
+            self.pos += 1;
+        }
+        let line = &self.data[start..self.pos];
+        self.pos += 1;
+        Some(std::str::from_utf8(line).unwrap().trim_end_matches('\r'))
+    }
+
+    fn nums(&mut self) -> Vec<u32> {
+        self.line()
+            .unwrap()
+            .split_whitespace()
+            .map(|s| s.parse().unwrap())
+            .collect()
+    }
+
+    fn num(&mut self) -> u32 {
+        self.nums()[0]
+    }
+
+    fn delta(&mut self) -> u32 {
+        let mut x = 0;
+        let mut i = 0;
+        loop {
+            let b = self.data[self.pos];
+            self.pos += 1;
+            x |= ((b & 0x7f) as u32) << (7 * i);
+            if b & 0x80 == 0 {
+                return x;
+            }
+            i += 1;
+        }
+    }
+}
+
+fn write_delta(out: &mut Vec<u8>, mut x: u32) {
+    while x & !0x7f != 0 {
+        out.push((x & 0x7f) as u8 | 0x80);
+        x >>= 7;
+    }
+    out.push(x as u8);
+}
+
+/// And-inverter graph collected when writing a transition system.
+#[derive(Default)]
+struct AigBuilder {
+    max_var: Var,
+    ands: Vec<(Lit, Lit, Lit)>,
+}
+
+impl AigBuilder {
+    fn and(&mut self, x: Lit, y: Lit) -> Lit {
+        if x.is_constant(false) || y.is_constant(false) || x == !y {
+            return Lit::constant(false);
+        }
+        if x.is_constant(true) || x == y {
+            return y;
+        }
+        if y.is_constant(true) {
+            return x;
+        }
+        self.max_var += 1;
+        let n = self.max_var.lit();
+        self.ands.push((n, x.max(y), x.min(y)));
+        n
+    }
+
+    fn ands(&mut self, lits: impl IntoIterator<Item = Lit>) -> Lit {
+        lits.into_iter()
+            .fold(Lit::constant(true), |acc, l| self.and(acc, l))
+    }
+
+    #[inline]
+    fn or(&mut self, x: Lit, y: Lit) -> Lit {
+        !self.and(!x, !y)
+    }
+
+    #[inline]
+    fn xor(&mut self, x: Lit, y: Lit) -> Lit {
+        let a = self.and(x, !y);
+        let b = self.and(!x, y);
+        self.or(a, b)
+    }
+
+    #[inline]
+    fn ite(&mut self, c: Lit, t: Lit, e: Lit) -> Lit {
+        let a = self.and(c, t);
+        let b = self.and(!c, e);
+        self.or(a, b)
+    }
+}
+
+struct Aiger {
+    input: Vec<Var>,
+    latch: Vec<(Var, Lit, Option<bool>)>,
+    ands: Vec<(Lit, Lit, Lit)>,
+    bad: LitVec,
+    constraint: LitVec,
+    justice: Vec<LitVec>,
+    fairness: LitVec,
+    max_var: Var,
+    symbols: Vec<String>,
+}
+
+impl Aiger {
+    fn header(&self, fmt: &str) -> String {
+        let mut h = format!(
+            "{fmt} {} {} {} 0 {} {}",
+            self.max_var,
+            self.input.len(),
+            self.latch.len(),
+            self.ands.len(),
+            self.bad.len()
+        );
+        if !self.constraint.is_empty() || !self.justice.is_empty() || !self.fairness.is_empty() {
+            h.push_str(&format!(
+                " {} {} {}",
+                self.constraint.len(),
+                self.justice.len(),
+                self.fairness.len()
+            ));
+        }
+        h.push('\n');
+        h
+    }
+
+    fn properties(&self) -> String {
+        let mut s = String::new();
+        let lit = |l: &Lit| format!("{}\n", u32::from(*l));
+        for l in self.bad.iter().chain(self.constraint.iter()) {
+            s.push_str(&lit(l));
+        }
+        for j in self.justice.iter() {
+            s.push_str(&format!("{}\n", j.len()));
+        }
+        for l in self.justice.iter().flatten().chain(self.fairness.iter()) {
+            s.push_str(&lit(l));
+        }
+        s
+    }
+
+    fn latch(&self, i: usize, binary: bool) -> String {
+        let (v, next, init) = self.latch[i];
+        let mut s = String::new();
+        if !binary {
+            s.push_str(&format!("{} ", u32::from(v.lit())));
+        }
+        s.push_str(&format!("{}", u32::from(next)));
+        match init {
+            Some(false) => (),
+            Some(true) => s.push_str(" 1"),
+            None => s.push_str(&format!(" {}", u32::from(v.lit()))),
+        }
+        s.push('\n');
+        s
+    }
+
+    fn aag(&self) -> String {
+        let mut s = self.header("aag");
+        for i in self.input.iter() {
+            s.push_str(&format!("{}\n", u32::from(i.lit())));
+        }
+        for i in 0..self.latch.len() {
+            s.push_str(&self.latch(i, false));
+        }
+        s.push_str(&self.properties());
+        for (n, x, y) in self.ands.iter() {
+            s.push_str(&format!(
+                "{} {} {}\n",
+                u32::from(*n),
+                u32::from(*x),
+                u32::from(*y)
+            ));
+        }
+        for sym in self.symbols.iter() {
+            s.push_str(sym);
+        }
+        s
+    }
+
+    fn aig(&self) -> Vec<u8> {
+        let mut s = self.header("aig");
+        for i in 0..self.latch.len() {
+            s.push_str(&self.latch(i, true));
+        }
+        s.push_str(&self.properties());
+        let mut out = s.into_bytes();
+        for (n, x, y) in self.ands.iter() {
+            let (n, x, y) = (u32::from(*n), u32::from(*x), u32::from(*y));
+            write_delta(&mut out, n - x);
+            write_delta(&mut out, x - y);
+        }
+        for sym in self.symbols.iter() {
+            out.extend_from_slice(sym.as_bytes());
+        }
+        out
+    }
+}
+
+impl TransitionSystem {
+    /// Read an AIGER model in the ASCII (`aag`) or binary (`aig`) format.
+    /// Vars are numbered inputs first, then latches, then and gates. If the
+    /// model has no bad properties, its outputs are taken as bad.
+    pub fn from_aiger(data: &[u8]) -> Self {
+        let mut r = Reader { data, pos: 0 };
+        let header = r.line().unwrap().to_string();
+        let mut header = header.split_whitespace();
+        let fmt = header.next().unwrap();
+        let binary = match fmt {
+            "aag" => false,
+            "aig" => true,
+            _ => panic!("unknown aiger format {fmt}"),
+        };
+        let h: Vec<usize> = header.map(|s| s.parse().unwrap()).collect();
+        assert!(h.len() >= 5);
+        let hn = |i: usize| h.get(i).copied().unwrap_or(0);
+        let (m, ni, nl, no, na) = (hn(0), hn(1), hn(2), hn(3), hn(4));
+        let (nb, nc, nj, nf) = (hn(5), hn(6), hn(7), hn(8));
+        let mut ts = TransitionSystem::new();
+        let mut amap: Vec<Option<Lit>> = vec![None; m + 1];
+        amap[0] = Some(Lit::constant(false));
+        let mut input = Vec::new();
+        for i in 0..ni {
+            let l = if binary { 2 * (i + 1) as u32 } else { r.num() };
+            input.push(l);
+            amap[l as usize >> 1] = Some(ts.new_input().lit());
+        }
+        let mut latch = Vec::new();
+        for i in 0..nl {
+            let mut line = r.nums();
+            if binary {
+                line.insert(0, 2 * (ni + i + 1) as u32);
+            }
+            let (l, next) = (line[0], line[1]);
+            let init = match line.get(2).copied().unwrap_or(0) {
+                0 => Some(false),
+                1 => Some(true),
+                x if x == l => None,
+                x => panic!("unsupported latch init {x}"),
+            };
+            latch.push((l, next));
+            amap[l as usize >> 1] = Some(ts.new_latch(init).lit());
+        }
+        let output: Vec<u32> = (0..no).map(|_| r.num()).collect();
+        let bad: Vec<u32> = (0..nb).map(|_| r.num()).collect();
+        let constraint: Vec<u32> = (0..nc).map(|_| r.num()).collect();
+        let jsize: Vec<u32> = (0..nj).map(|_| r.num()).collect();
+        let justice: Vec<Vec<u32>> = jsize
+            .iter()
+            .map(|&s| (0..s).map(|_| r.num()).collect())
+            .collect();
+        let fairness: Vec<u32> = (0..nf).map(|_| r.num()).collect();
+        let mut def: Vec<Option<(u32, u32)>> = vec![None; m + 1];
+        let mut ands = Vec::new();
+        for i in 0..na {
+            let (n, x, y) = if binary {
+                let n = 2 * (ni + nl + i + 1) as u32;
+                let x = n - r.delta();
+                (n, x, x - r.delta())
+            } else {
+                let line = r.nums();
+                (line[0], line[1], line[2])
+            };
+            def[n as usize >> 1] = Some((x, y));
+            ands.push(n as usize >> 1);
+        }
+        let get =
+            |amap: &[Option<Lit>], l: u32| amap[l as usize >> 1].map(|x| x.not_if(l & 1 == 1));
+        for a in ands {
+            let mut stack = vec![a];
+            while let Some(&v) = stack.last() {
+                if amap[v].is_some() {
+                    stack.pop();
+                    continue;
+                }
+                let (x, y) = def[v].unwrap();
+                match (get(&amap, x), get(&amap, y)) {
+                    (Some(x), Some(y)) => {
+                        let n = if x == !y {
+                            Lit::constant(false)
+                        } else if x == y {
+                            x
+                        } else {
+                            ts.rel.new_and([x, y])
+                        };
+                        amap[v] = Some(n);
+                        stack.pop();
+                    }
+                    (x_lit, y_lit) => {
+                        if x_lit.is_none() {
+                            stack.push(x as usize >> 1);
+                        }
+                        if y_lit.is_none() {
+                            stack.push(y as usize >> 1);
+                        }
+                    }
+                }
+            }
+        }
+        let lit = |l: u32| get(&amap, l).unwrap();
+        for (l, next) in latch {
+            ts.set_next(lit(l).var(), lit(next));
+        }
+        let bad = if bad.is_empty() { output } else { bad };
+        ts.bad = bad.into_iter().map(lit).collect();
+        ts.constraint = constraint.into_iter().map(lit).collect();
+        ts.justice = justice
+            .into_iter()
+            .map(|j| j.into_iter().map(lit).collect())
+            .collect();
+        ts.fairness = fairness.into_iter().map(lit).collect();
+        let mut seen = GHashSet::new();
+        while let Some(line) = r.line() {
+            if line == "c" {
+                break;
+            }
+            let Some((pos, name)) = line.split_once(' ') else {
+                continue;
+            };
+            let v = match (&pos[..1], pos[1..].parse::<usize>()) {
+                ("i", Ok(i)) if i < ni => lit(input[i]).var(),
+                ("l", Ok(i)) if i < nl => ts.latch[i],
+                _ => continue,
+            };
+            let (name, idx) = parse_symbol(name);
+            if seen.insert((name.clone(), idx)) {
+                ts.symbols.insert(v, name, idx);
+            }
+        }
+        ts
+    }
+
+    pub fn from_aiger_file<P: AsRef<Path>>(file: P) -> Self {
+        Self::from_aiger(&read(file).unwrap())
+    }
+
+    fn to_aiger(&self) -> Aiger {
+        let mut b = AigBuilder::default();
+        let mut amap = VarLMap::new();
+        amap.insert(Var::CONST, Lit::constant(false));
+        let latch = GHashSet::from_iter(self.latch.iter().copied());
+        let mut input = self.input.clone();
+        for v in self.rel.var_iter_woc() {
+            if self.rel.is_leaf(v) && !latch.contains(&v) && !input.contains(&v) {
+                input.push(v);
+            }
+        }
+        for v in input.iter() {
+            b.max_var += 1;
+            amap.insert(*v, b.max_var.lit());
+        }
+        // a latch without next is free, its next is a fresh input
+        let mut free = Vec::new();
+        let mut free_next = VarLMap::new();
+        for &l in self.latch.iter().filter(|&&l| self.next.map(l).is_none()) {
+            b.max_var += 1;
+            free.push(b.max_var);
+            free_next.insert(l, b.max_var.lit());
+        }
+        for v in self.latch.iter() {
+            b.max_var += 1;
+            amap.insert(*v, b.max_var.lit());
+        }
+        for v in self.rel.var_iter_woc() {
+            let Some(g) = self.rel.gate(v) else {
+                continue;
+            };
+            let ml = |l: &Lit| amap.map_lit(*l).unwrap();
+            let n = match g {
+                Gate::And(l) => b.ands(l.iter().map(ml)),
+                Gate::Or(l) => !b.ands(l.iter().map(|l| !ml(l))),
+                Gate::Xor(x, y) => b.xor(ml(&x), ml(&y)),
+                Gate::Xnor(x, y) => !b.xor(ml(&x), ml(&y)),
+                Gate::Ite(c, t, e) => b.ite(ml(&c), ml(&t), ml(&e)),
+                Gate::Majority(x, y, z) => {
+                    let (x, y, z) = (ml(&x), ml(&y), ml(&z));
+                    let o = b.or(y, z);
+                    let a = b.and(y, z);
+                    b.ite(x, o, a)
+                }
+                Gate::Equiv(l) => ml(&l),
+                Gate::Unknown => {
+                    let mut dep: Vec<Var> = self
+                        .rel
+                        .dep(v)
+                        .iter()
+                        .copied()
+                        .filter(|d| !d.is_constant())
+                        .collect();
+                    dep.sort();
+                    let f = (dep.len() <= GATE_TT_MAX_SUPPORT)
+                        .then(|| self.rel.truth_table(v, &dep))
+                        .flatten();
+                    let mut n = Lit::constant(false);
+                    if let Some(f) = f {
+                        for a in (0..1usize << dep.len()).filter(|a| f >> a & 1 == 1) {
+                            let m = b.ands(
+                                dep.iter()
+                                    .enumerate()
+                                    .map(|(i, d)| ml(&d.lit()).not_if(a >> i & 1 == 0)),
+                            );
+                            n = b.or(n, m);
+                        }
+                    } else {
+                        // A total function is true exactly where one of its
+                        // positive clauses forces it.
+                        for cls in self.rel[v].iter().filter(|c| c.contains(&v.lit())) {
+                            let m = b.ands(cls.iter().filter(|l| l.var() != v).map(|l| !ml(l)));
+                            n = b.or(n, m);
+                        }
+                    }
+                    n
+                }
+            };
+            amap.insert(v, n);
+        }
+        let ml = |l: &Lit| amap.map_lit(*l).unwrap();
+        let mut symbols = Vec::new();
+        for (t, vars) in [("i", &input), ("l", &self.latch)] {
+            for (i, v) in vars.iter().enumerate() {
+                if let Some((name, idx)) = self.symbols.get(*v).into_iter().next() {
+                    let name = if idx == 0 {
+                        name
+                    } else {
+                        format!("{name}[{idx}]")
+                    };
+                    symbols.push(format!("{t}{i} {name}\n"));
+                }
+            }
+        }
+        Aiger {
+            latch: self
+                .latch
+                .iter()
+                .map(|&l| {
+                    let next = self.next.map(l).map_or_else(|| free_next[&l], |n| ml(&n));
+                    (amap[&l].var(), next, self.init.get(&l).copied())
+                })
+                .collect(),
+            input: input.iter().map(|i| amap[i].var()).chain(free).collect(),
+            ands: b.ands,
+            bad: self.bad.iter().map(ml).collect(),
+            constraint: self.constraint.iter().map(ml).collect(),
+            justice: self
+                .justice
+                .iter()
+                .map(|j| j.iter().map(ml).collect())
+                .collect(),
+            fairness: self.fairness.iter().map(ml).collect(),
+            max_var: b.max_var,
+            symbols,
+        }
+    }
+
+    /// Write in the ASCII AIGER format. Relations are translated through
+    /// their gates, or their truth table or positive clauses when no gate
+    /// matches, so they must be total functions of their fanins.
+    pub fn to_aag(&self) -> String {
+        self.to_aiger().aag()
+    }
+
+    /// Write in the binary AIGER format.
+    pub fn to_aig(&self) -> Vec<u8> {
+        self.to_aiger().aig()
+    }
+
+    /// The format is chosen by the extension, `.aig` is binary.
+    pub fn to_aiger_file<P: AsRef<Path>>(&self, file: P) {
+        let data = if file.as_ref().extension().is_some_and(|e| e == "aig") {
+            self.to_aig()
+        } else {
+            self.to_aag().into_bytes()
+        };
+        let mut file = File::create(file).unwrap();
+        file.write_all(&data).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        Lit, LitVec, VarAssign,
+        ts::{TransitionSystem, test::counter},
+    };
+
+    #[test]
+    fn test_aiger() {
+        let ts = counter();
+        let aag = ts.to_aag();
+        assert!(aag.starts_with("aag 12 1 3 0 8 1\n"));
+        let rts = TransitionSystem::from_aiger(aag.as_bytes());
+        assert_eq!(rts.input.len(), 1);
+        assert_eq!(rts.latch.len(), 3);
+        assert_eq!(rts.init.len(), 2);
+        assert_eq!(rts.to_aag(), aag);
+        assert_eq!(rts.symbols.get(rts.latch[1]), vec![("cnt".to_string(), 1)]);
+        let bts = TransitionSystem::from_aiger(&ts.to_aig());
+        assert_eq!(bts.to_aag(), aag);
+        let coi = TransitionSystem::from_aiger(aag.as_bytes()).coi();
+        assert_eq!(coi.latch.len(), 2);
+    }
+
+    #[test]
+    fn test_aiger_large_support() {
+        let mut ts = TransitionSystem::new();
+        let i: Vec<Lit> = (0..7).map(|_| ts.new_input().lit()).collect();
+        let v = ts.rel.new_var();
+        let mut rel = vec![
+            LitVec::from([!i[0], !i[1], v.lit()]),
+            LitVec::from([!i[2], !i[3], v.lit()]),
+            LitVec::from([!i[4], !i[5], !i[6], v.lit()]),
+        ];
+        for x in [i[0], i[1]] {
+            for y in [i[2], i[3]] {
+                for z in [i[4], i[5], i[6]] {
+                    rel.push(LitVec::from([x, y, z, !v.lit()]));
+                }
+            }
+        }
+        ts.rel.add_rel(v, &rel);
+        ts.bad.push(v.lit());
+        let rts = TransitionSystem::from_aiger(ts.to_aag().as_bytes());
+        for a in 0..1usize << 7 {
+            let mut assign = VarAssign::new_with(ts.max_var());
+            let mut rassign = VarAssign::new_with(rts.max_var());
+            for (k, (l, r)) in i.iter().zip(rts.input.iter()).enumerate() {
+                assign.set(l.not_if(a >> k & 1 == 0));
+                rassign.set(r.lit().not_if(a >> k & 1 == 0));
+            }
+            let val = ts.rel.ternary_simulation(&assign).val(ts.bad[0]);
+            let rval = rts.rel.ternary_simulation(&rassign).val(rts.bad[0]);
+            assert_eq!(val, rval);
+        }
+    }
+
+    #[test]
+    fn test_aiger_free_latch() {
+        let mut ts = TransitionSystem::new();
+        let l = ts.new_latch(Some(false));
+        ts.bad.push(l.lit());
+        let rts = TransitionSystem::from_aiger(ts.to_aag().as_bytes());
+        assert_eq!(rts.input.len(), 1);
+        assert_eq!(rts.next[&rts.latch[0]], rts.input[0].lit());
+        assert_eq!(rts.init.get(&rts.latch[0]), Some(&false));
+    }
+}
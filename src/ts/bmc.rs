@@ -0,0 +1,110 @@
+use super::{Trace, TransitionSystem, TsUnroll};
+use crate::{Lit, Var, satif::Satif};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BmcResult {
+    /// Bad is reached in the last frame of the trace.
+    Cex(Trace),
+    /// No counterexample of depth at most `k`.
+    NoCex(usize),
+}
+
+/// Bounded model checking. Frames are unrolled incrementally into one
+/// solver, and bad of depth `k` is checked under an assumption literal.
+pub struct Bmc<S: Satif> {
+    uts: TsUnroll,
+    solver: S,
+    loaded: Var,
+    bad: Vec<Lit>,
+}
+
+impl<S: Satif> Bmc<S> {
+    pub fn new(ts: &TransitionSystem, mut solver: S) -> Self {
+        solver.add_clause(&[Lit::constant(true)]);
+        let mut bmc = Self {
+            uts: TsUnroll::new(ts.clone()),
+            solver,
+            loaded: Var::CONST,
+            bad: Vec::new(),
+        };
+        bmc.extend();
+        for l in bmc.uts.ts.init_cube() {
+            let l = bmc.uts.lit(l, 0);
+            bmc.solver.add_clause(&[l]);
+        }
+        bmc
+    }
+
+    /// Unroll the next frame and load it into the solver.
+    fn extend(&mut self) {
+        self.uts.unroll();
+        let k = self.uts.num_frame() - 1;
+        let bad = self.uts.lits(&self.uts.ts.bad, k);
+        let bad = self.uts.dc.new_or(bad);
+        self.bad.push(bad);
+        self.loaded = self.uts.load(&mut self.solver, self.loaded);
+        for c in self.uts.lits(&self.uts.ts.constraint, k) {
+            self.solver.add_clause(&[c]);
+        }
+    }
+
+    #[inline]
+    pub fn unroll(&self) -> &TsUnroll {
+        &self.uts
+    }
+
+    /// Trace of the last satisfiable query, with `k + 1` frames.
+    pub fn trace(&self, k: usize) -> Trace {
+        let mut trace = Trace::default();
+        for i in 0..=k {
+            trace.push_model(&self.uts.ts, &self.solver, |l| self.uts.lit(l, i));
+        }
+        trace
+    }
+
+    /// Check for a counterexample of depth exactly `k`. Depths must be
+    /// checked in increasing order, since bad of a depth without
+    /// counterexample is blocked afterwards.
+    pub fn check_depth(&mut self, k: usize) -> Option<Trace> {
+        while self.uts.num_frame() <= k {
+            self.extend();
+        }
+        if self.solver.solve(&[self.bad[k]]) {
+            return Some(self.trace(k));
+        }
+        self.solver.add_clause(&[!self.bad[k]]);
+        None
+    }
+
+    pub fn check(&mut self, max_k: usize) -> BmcResult {
+        for k in 0..=max_k {
+            if let Some(trace) = self.check_depth(k) {
+                return BmcResult::Cex(trace);
+            }
+        }
+        BmcResult::NoCex(max_k)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Bmc, BmcResult};
+    use crate::{LboolVec, satif::test::DpllSolver, ts::test::counter};
+
+    #[test]
+    fn test_bmc() {
+        let ts = counter();
+        let mut bmc = Bmc::new(&ts, DpllSolver::new());
+        assert_eq!(bmc.check(2), BmcResult::NoCex(2));
+        let BmcResult::Cex(trace) = bmc.check(5) else {
+            panic!()
+        };
+        assert_eq!(trace.len(), 4);
+        let last = trace.symbol_assigns(&ts.symbols, 3);
+        assert_eq!(last.len(), 2);
+        assert_eq!(last[0].symbol, "cnt");
+        assert_eq!(last[0].assign, LboolVec::from("11"));
+        let first = trace.symbol_assigns(&ts.symbols, 0);
+        assert_eq!(first[0].assign, LboolVec::from("00"));
+    }
+}
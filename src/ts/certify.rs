@@ -0,0 +1,180 @@
+use super::{Trace, TransitionSystem, TsUnroll};
+use crate::{Lit, LitOrdVec, LitVec, Var, satif::Satif};
+
+/// Failed check of an invariant certificate. The witness holds one frame,
+/// or the two frames of the transition for consecution. `clause` is the
+/// index in the invariant of a clause false in the last frame, None if the
+/// solver model makes no clause false.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InvariantViolation {
+    /// An initial state violates clause `clause` of the invariant.
+    Initiation {
+        clause: Option<usize>,
+        witness: Trace,
+    },
+    /// A transition from the invariant reaches a state that violates clause
+    /// `clause`.
+    Consecution {
+        clause: Option<usize>,
+        witness: Trace,
+    },
+    /// A state of the invariant violates the property.
+    Safety { witness: Trace },
+}
+
+struct Checker<'a, S: Satif> {
+    uts: TsUnroll,
+    inv: &'a [LitVec],
+    new_solver: &'a mut dyn FnMut() -> S,
+}
+
+impl<S: Satif> Checker<'_, S> {
+    /// Solver with the unrolling, the constraints of frames up to `k`, and
+    /// the invariant at frame 0 unless `init`.
+    fn solver(&mut self, k: usize, init: bool) -> S {
+        let mut solver = (self.new_solver)();
+        solver.add_clause(&[Lit::constant(true)]);
+        self.uts.load(&mut solver, Var::CONST);
+        for i in 0..=k {
+            for c in self.uts.lits(&self.uts.ts.constraint, i) {
+                solver.add_clause(&[c]);
+            }
+        }
+        if init {
+            for l in self.uts.lits(&self.uts.ts.init_cube(), 0) {
+                solver.add_clause(&[l]);
+            }
+        } else {
+            for cls in self.inv.iter() {
+                solver.add_clause(&self.uts.lits(cls, 0));
+            }
+        }
+        solver
+    }
+
+    /// Literal true iff the invariant is violated at frame `k`.
+    fn violated(&mut self, k: usize) -> Lit {
+        let mut ors = Vec::new();
+        for cls in self.inv.iter() {
+            let cls = !self.uts.lits(cls, k);
+            ors.push(self.uts.dc.new_and(cls));
+        }
+        self.uts.dc.new_or(ors)
+    }
+
+    fn witness(&self, solver: &S, k: usize) -> (Option<usize>, Trace) {
+        let mut witness = Trace::default();
+        for i in 0..=k {
+            witness.push_model(&self.uts.ts, solver, |l| self.uts.lit(l, i));
+        }
+        let clause = self.inv.iter().position(|cls| {
+            self.uts
+                .lits(cls, k)
+                .iter()
+                .all(|l| solver.sat_value(*l) == Some(false))
+        });
+        (clause, witness)
+    }
+}
+
+impl TransitionSystem {
+    /// Check that `inv`, a CNF over the vars of the system, is an inductive
+    /// invariant that implies `property`: it holds in the initial states, it
+    /// is preserved by the transitions, and it excludes the states violating
+    /// `property`. Each check is a query to a fresh solver. Violated clauses
+    /// are reported by their index in `inv`, a `Cnf` passed by deref starts
+    /// with its constant clause.
+    pub fn certify<S: Satif>(
+        &self,
+        property: Lit,
+        inv: &[LitVec],
+        mut new_solver: impl FnMut() -> S,
+    ) -> Result<(), InvariantViolation> {
+        let mut uts = TsUnroll::new(self.clone());
+        uts.unroll_to(1);
+        let mut checker = Checker {
+            uts,
+            inv,
+            new_solver: &mut new_solver,
+        };
+        let v0 = checker.violated(0);
+        let v1 = checker.violated(1);
+
+        let mut solver = checker.solver(0, true);
+        if solver.solve(&[v0]) {
+            let (clause, witness) = checker.witness(&solver, 0);
+            return Err(InvariantViolation::Initiation { clause, witness });
+        }
+
+        let mut solver = checker.solver(1, false);
+        if solver.solve(&[v1]) {
+            let (clause, witness) = checker.witness(&solver, 1);
+            return Err(InvariantViolation::Consecution { clause, witness });
+        }
+
+        let mut solver = checker.solver(0, false);
+        if solver.solve(&[!checker.uts.lit(property, 0)]) {
+            let (_, witness) = checker.witness(&solver, 0);
+            return Err(InvariantViolation::Safety { witness });
+        }
+        Ok(())
+    }
+
+    /// Same as `certify`, with the invariant given as blocked cubes.
+    /// Violated clauses are reported by the index of their lemma.
+    pub fn certify_lemmas<S: Satif>(
+        &self,
+        property: Lit,
+        lemmas: &[LitOrdVec],
+        new_solver: impl FnMut() -> S,
+    ) -> Result<(), InvariantViolation> {
+        let inv: Vec<LitVec> = lemmas.iter().map(|c| !c.as_litvec()).collect();
+        self.certify(property, &inv, new_solver)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::InvariantViolation;
+    use crate::{
+        Lit, LitOrdVec, LitVec,
+        satif::test::DpllSolver,
+        ts::{Ic3, Ic3Result, TransitionSystem},
+    };
+
+    #[test]
+    fn test_certify() {
+        let mut ts = TransitionSystem::new();
+        let x = ts.new_latch(Some(false)).lit();
+        let y = ts.new_latch(Some(false)).lit();
+        ts.set_next(x.var(), y);
+        ts.set_next(y.var(), y);
+        ts.bad.push(x);
+        let Ic3Result::Safe(inv) = Ic3::new(&ts, DpllSolver::new).check() else {
+            panic!()
+        };
+        assert!(ts.certify(!x, &inv, DpllSolver::new).is_ok());
+        let lemmas = [LitOrdVec::from([x]), LitOrdVec::from([y])];
+        assert!(ts.certify_lemmas(!x, &lemmas, DpllSolver::new).is_ok());
+
+        let res = ts.certify(!x, &[LitVec::from([!x])], DpllSolver::new);
+        let Err(InvariantViolation::Consecution { clause, witness }) = res else {
+            panic!()
+        };
+        assert_eq!(clause, Some(0));
+        assert_eq!(witness.state[0], LitVec::from([!x, y]));
+        assert_eq!(witness.state[1], LitVec::from([x, y]));
+
+        let lemmas = [LitOrdVec::from([x, !y]), LitOrdVec::from([x])];
+        let res = ts.certify_lemmas(!x, &lemmas, DpllSolver::new);
+        let Err(InvariantViolation::Consecution { clause, .. }) = res else {
+            panic!()
+        };
+        assert_eq!(clause, Some(1));
+
+        let res = ts.certify(!x, &[LitVec::from([x])], DpllSolver::new);
+        assert!(matches!(res, Err(InvariantViolation::Initiation { .. })));
+        let res = ts.certify(!x, &[LitVec::from([Lit::constant(true)])], DpllSolver::new);
+        assert!(matches!(res, Err(InvariantViolation::Safety { .. })));
+    }
+}
@@ -0,0 +1,328 @@
+use super::{Trace, TransitionSystem, TsUnroll};
+use crate::{Cnf, Lit, LitOrdVec, LitVec, Var, lemmas_subsume_simplify, satif::Satif};
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+#[derive(Clone, Debug)]
+pub enum Ic3Result {
+    /// Inductive invariant over the latch vars, which excludes bad.
+    Safe(Cnf),
+    Unsafe(Trace),
+}
+
+/// Proof obligation: a state that has to be blocked at `frame`. `input`
+/// leads from the state to the obligation `next`, or to bad if it is None.
+#[derive(Clone, Debug)]
+struct Obligation {
+    frame: usize,
+    cube: LitOrdVec,
+    input: LitVec,
+    next: Option<usize>,
+}
+
+/// IC3/PDR. `frames[i]` holds the cubes first blocked at frame `i`; the
+/// solver of frame `i` holds the lemmas of all frames from `i` on, plus the
+/// transition relation as a two-frame unrolling. The solver of frame 0
+/// holds the initial states instead.
+pub struct Ic3<S: Satif> {
+    uts: TsUnroll,
+    bad: Lit,
+    init: LitVec,
+    /// Constraints of frame 1, assumed in relative induction queries.
+    next_constraint: LitVec,
+    new_solver: Box<dyn FnMut() -> S>,
+    solvers: Vec<S>,
+    frames: Vec<Vec<LitOrdVec>>,
+    obligations: Vec<Obligation>,
+}
+
+impl<S: Satif> Ic3<S> {
+    pub fn new(ts: &TransitionSystem, new_solver: impl FnMut() -> S + 'static) -> Self {
+        let mut uts = TsUnroll::new(ts.clone());
+        uts.unroll_to(1);
+        let bad = uts.lits(&ts.bad, 0);
+        let bad = uts.dc.new_or(bad);
+        Self {
+            next_constraint: uts.lits(&ts.constraint, 1),
+            uts,
+            bad,
+            init: ts.init_cube(),
+            new_solver: Box::new(new_solver),
+            solvers: Vec::new(),
+            frames: Vec::new(),
+            obligations: Vec::new(),
+        }
+    }
+
+    #[inline]
+    fn level(&self) -> usize {
+        self.solvers.len() - 1
+    }
+
+    fn new_frame(&mut self) {
+        let mut solver = (self.new_solver)();
+        solver.add_clause(&[Lit::constant(true)]);
+        self.uts.load(&mut solver, Var::CONST);
+        for c in self.uts.lits(&self.uts.ts.constraint, 0) {
+            solver.add_clause(&[c]);
+        }
+        if self.solvers.is_empty() {
+            for i in self.uts.lits(&self.init, 0) {
+                solver.add_clause(&[i]);
+            }
+        }
+        self.solvers.push(solver);
+        self.frames.push(Vec::new());
+    }
+
+    #[inline]
+    fn intersect_init(&self, cube: &[Lit]) -> bool {
+        !cube.iter().any(|l| self.init.contains(&!*l))
+    }
+
+    /// Latch and input assignments of frame 0 in the model of `solver`.
+    fn model(&self, solver: usize) -> (LitOrdVec, LitVec) {
+        let value = |vs: &[Var]| -> LitVec {
+            vs.iter()
+                .filter_map(|v| {
+                    self.solvers[solver]
+                        .sat_value(self.uts.lit(v.lit(), 0))
+                        .map(|b| v.lit().not_if(!b))
+                })
+                .collect()
+        };
+        (
+            LitOrdVec::new(value(&self.uts.ts.latch)),
+            value(&self.uts.ts.input),
+        )
+    }
+
+    /// Check whether `cube` is inductive relative to frame `i - 1`, with
+    /// the clause of `cube` added to the frame if `strengthen`. Returns the
+    /// literals of `cube` in the unsat core, or a predecessor of `cube`.
+    fn inductive(
+        &mut self,
+        i: usize,
+        cube: &[Lit],
+        strengthen: bool,
+    ) -> Result<LitVec, (LitOrdVec, LitVec)> {
+        let mut assump = self.uts.lits(cube, 1);
+        assump.extend(self.next_constraint.iter().copied());
+        let act = strengthen.then(|| {
+            let act = self.solvers[i - 1].new_var().lit();
+            let mut cls = !self.uts.lits(cube, 0);
+            cls.push(!act);
+            self.solvers[i - 1].add_clause(&cls);
+            act
+        });
+        assump.extend(act);
+        let res = if self.solvers[i - 1].solve(&assump) {
+            Err(self.model(i - 1))
+        } else {
+            let solver = &self.solvers[i - 1];
+            Ok(cube
+                .iter()
+                .zip(assump.iter())
+                .filter(|(_, n)| solver.unsat_has(**n))
+                .map(|(c, _)| *c)
+                .collect())
+        };
+        if let Some(act) = act {
+            self.solvers[i - 1].add_clause(&[!act]);
+        }
+        res
+    }
+
+    /// Add back a literal of `cube` that excludes the initial states.
+    fn fix_init(&self, mut core: LitVec, cube: &[Lit]) -> LitVec {
+        if self.intersect_init(&core) {
+            let l = cube.iter().find(|l| self.init.contains(&!**l)).unwrap();
+            core.push(*l);
+        }
+        core
+    }
+
+    /// Drop literals of `cube` as long as it stays inductive relative to
+    /// frame `i - 1`.
+    fn mic(&mut self, i: usize, mut cube: LitVec) -> LitVec {
+        let mut j = 0;
+        while j < cube.len() {
+            let mut cand = cube.clone();
+            cand.remove(j);
+            if self.intersect_init(&cand) {
+                j += 1;
+                continue;
+            }
+            match self.inductive(i, &cand, true) {
+                Ok(core) => cube = self.fix_init(core, &cand),
+                Err(_) => j += 1,
+            }
+        }
+        cube
+    }
+
+    fn add_lemma(&mut self, i: usize, cube: LitOrdVec) {
+        for f in self.frames[1..=i].iter_mut() {
+            f.retain(|c| !cube.subsume(c));
+        }
+        let cls = !self.uts.lits(&cube, 0);
+        for s in self.solvers[1..=i].iter_mut() {
+            s.add_clause(&cls);
+        }
+        self.frames[i].push(cube);
+    }
+
+    fn blocked(&self, i: usize, cube: &LitOrdVec) -> bool {
+        self.frames[i..].iter().flatten().any(|c| c.subsume(cube))
+    }
+
+    fn trace(&self, mut po: usize) -> Trace {
+        let mut trace = Trace::default();
+        loop {
+            let o = &self.obligations[po];
+            trace.state.push(o.cube.as_litvec().clone());
+            trace.input.push(o.input.clone());
+            match o.next {
+                Some(n) => po = n,
+                None => return trace,
+            }
+        }
+    }
+
+    fn new_obligation(&mut self, po: Obligation, queue: &mut BinaryHeap<Reverse<(usize, usize)>>) {
+        queue.push(Reverse((po.frame, self.obligations.len())));
+        self.obligations.push(po);
+    }
+
+    /// Block the obligations until none is left, or one of them is reachable
+    /// from the initial states.
+    fn block(&mut self, po: Obligation) -> Option<Trace> {
+        let mut queue = BinaryHeap::new();
+        self.new_obligation(po, &mut queue);
+        while let Some(Reverse((frame, po))) = queue.pop() {
+            let cube = self.obligations[po].cube.clone();
+            if self.blocked(frame, &cube) {
+                if frame < self.level() {
+                    queue.push(Reverse((frame + 1, po)));
+                }
+                continue;
+            }
+            match self.inductive(frame, &cube, true) {
+                Ok(core) => {
+                    let core = self.fix_init(core, &cube);
+                    let lemma = self.mic(frame, core);
+                    let mut f = frame;
+                    while f < self.level() && self.inductive(f + 1, &lemma, true).is_ok() {
+                        f += 1;
+                    }
+                    self.add_lemma(f, LitOrdVec::new(lemma));
+                    if f < self.level() {
+                        queue.push(Reverse((f + 1, po)));
+                    }
+                }
+                Err((cube, input)) => {
+                    let init = self.intersect_init(&cube);
+                    queue.push(Reverse((frame, po)));
+                    self.new_obligation(
+                        Obligation {
+                            frame: frame - 1,
+                            cube,
+                            input,
+                            next: Some(po),
+                        },
+                        &mut queue,
+                    );
+                    if init {
+                        return Some(self.trace(self.obligations.len() - 1));
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// Push lemmas to later frames. Returns the invariant once two frames
+    /// become equal.
+    fn propagate(&mut self) -> Option<Cnf> {
+        let level = self.level();
+        for i in 1..level {
+            for cube in self.frames[i].clone() {
+                if self.inductive(i + 1, &cube, false).is_ok() {
+                    self.frames[i].retain(|c| *c != cube);
+                    let cls = !self.uts.lits(&cube, 0);
+                    self.solvers[i + 1].add_clause(&cls);
+                    self.frames[i + 1].push(cube);
+                }
+            }
+            if self.frames[i].is_empty() {
+                let mut inv = Cnf::new();
+                inv.new_var_to(self.uts.ts.max_var());
+                let lemmas = self.frames[i + 1..].iter().flatten().cloned().collect();
+                for c in lemmas_subsume_simplify(lemmas) {
+                    inv.add_clause(&!c.as_litvec());
+                }
+                return Some(inv);
+            }
+        }
+        None
+    }
+
+    pub fn check(&mut self) -> Ic3Result {
+        self.new_frame();
+        loop {
+            let k = self.level();
+            while self.solvers[k].solve(&[self.bad]) {
+                let (cube, input) = self.model(k);
+                let po = Obligation {
+                    frame: k,
+                    cube,
+                    input,
+                    next: None,
+                };
+                if self.intersect_init(&po.cube) {
+                    self.obligations.push(po);
+                    return Ic3Result::Unsafe(self.trace(self.obligations.len() - 1));
+                }
+                if let Some(trace) = self.block(po) {
+                    return Ic3Result::Unsafe(trace);
+                }
+            }
+            self.new_frame();
+            if let Some(inv) = self.propagate() {
+                return Ic3Result::Safe(inv);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Ic3, Ic3Result};
+    use crate::{
+        Lit, LitVec,
+        satif::test::DpllSolver,
+        ts::{TransitionSystem, test::counter},
+    };
+
+    #[test]
+    fn test_ic3() {
+        let mut ic3 = Ic3::new(&counter(), DpllSolver::new);
+        let Ic3Result::Unsafe(trace) = ic3.check() else {
+            panic!()
+        };
+        assert_eq!(trace.len(), 4);
+        assert_eq!(trace.state[0][..2], [Lit::from(-2), Lit::from(-3)]);
+
+        let mut ts = TransitionSystem::new();
+        let x = ts.new_latch(Some(false));
+        let y = ts.new_latch(Some(false));
+        ts.set_next(x, y.lit());
+        ts.set_next(y, y.lit());
+        ts.bad.push(x.lit());
+        let mut ic3 = Ic3::new(&ts, DpllSolver::new);
+        let Ic3Result::Safe(inv) = ic3.check() else {
+            panic!()
+        };
+        assert!(inv.contains(&LitVec::from([!x.lit()])));
+        assert!(inv.contains(&LitVec::from([!y.lit()])));
+    }
+}
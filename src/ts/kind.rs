@@ -0,0 +1,177 @@
+use super::{Bmc, Trace, TransitionSystem, TsUnroll};
+use crate::{Lit, Var, satif::Satif};
+
+#[derive(Clone, Debug)]
+pub struct KindConfig {
+    pub max_k: usize,
+    /// Require the states of the inductive step to be pairwise different.
+    /// The constraints are added lazily, only for pairs of equal states
+    /// found in a model.
+    pub simple_path: bool,
+}
+
+impl Default for KindConfig {
+    fn default() -> Self {
+        Self {
+            max_k: 20,
+            simple_path: true,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum KindResult {
+    /// Bad is unreachable, proved by induction of depth `k`.
+    Proved(usize),
+    Cex(Trace),
+    /// Neither proved nor refuted up to the max depth.
+    Unknown(usize),
+}
+
+/// k-induction. The base case is a BMC run, the inductive step unrolls
+/// frames without the initial states in a second solver.
+pub struct Kind<S: Satif> {
+    bmc: Bmc<S>,
+    uts: TsUnroll,
+    solver: S,
+    loaded: Var,
+    bad: Vec<Lit>,
+    config: KindConfig,
+}
+
+impl<S: Satif> Kind<S> {
+    pub fn new(ts: &TransitionSystem, mut new_solver: impl FnMut() -> S) -> Self {
+        let bmc = Bmc::new(ts, new_solver());
+        let mut solver = new_solver();
+        solver.add_clause(&[Lit::constant(true)]);
+        Self {
+            bmc,
+            uts: TsUnroll::new(ts.clone()),
+            solver,
+            loaded: Var::CONST,
+            bad: Vec::new(),
+            config: KindConfig::default(),
+        }
+    }
+
+    #[inline]
+    pub fn set_config(&mut self, config: KindConfig) {
+        self.config = config;
+    }
+
+    /// Unroll the next frame of the step case, bad of the previous frame is
+    /// assumed false from now on.
+    fn extend(&mut self) {
+        if let Some(&b) = self.bad.last() {
+            self.solver.add_clause(&[!b]);
+        }
+        self.uts.unroll();
+        let k = self.uts.num_frame() - 1;
+        let bad = self.uts.lits(&self.uts.ts.bad, k);
+        let bad = self.uts.dc.new_or(bad);
+        self.bad.push(bad);
+        self.loaded = self.uts.load(&mut self.solver, self.loaded);
+        for c in self.uts.lits(&self.uts.ts.constraint, k) {
+            self.solver.add_clause(&[c]);
+        }
+    }
+
+    /// A pair of frames with equal states in the current model.
+    fn equal_states(&self) -> Option<(usize, usize)> {
+        let state = |k: usize| -> Vec<Option<bool>> {
+            self.uts
+                .ts
+                .latch
+                .iter()
+                .map(|l| self.solver.sat_value(self.uts.lit(l.lit(), k)))
+                .collect()
+        };
+        let states: Vec<_> = (0..self.uts.num_frame()).map(state).collect();
+        for j in 1..states.len() {
+            for i in 0..j {
+                if states[i] == states[j] {
+                    return Some((i, j));
+                }
+            }
+        }
+        None
+    }
+
+    /// Inductive step of depth `k`: whether bad is unreachable from any
+    /// path of `k` frames without bad.
+    pub fn step(&mut self, k: usize) -> bool {
+        while self.uts.num_frame() <= k {
+            self.extend();
+        }
+        loop {
+            if !self.solver.solve(&[self.bad[k]]) {
+                return true;
+            }
+            if !self.config.simple_path {
+                return false;
+            }
+            let Some((i, j)) = self.equal_states() else {
+                return false;
+            };
+            let mut diff = Vec::new();
+            for l in self.uts.ts.latch.clone() {
+                let (x, y) = (self.uts.lit(l.lit(), i), self.uts.lit(l.lit(), j));
+                diff.push(self.uts.dc.new_xor(x, y));
+            }
+            let diff = self.uts.dc.new_or(diff);
+            self.loaded = self.uts.load(&mut self.solver, self.loaded);
+            self.solver.add_clause(&[diff]);
+        }
+    }
+
+    pub fn check(&mut self) -> KindResult {
+        for k in 0..=self.config.max_k {
+            if let Some(trace) = self.bmc.check_depth(k) {
+                return KindResult::Cex(trace);
+            }
+            if self.step(k) {
+                return KindResult::Proved(k);
+            }
+        }
+        KindResult::Unknown(self.config.max_k)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Kind, KindConfig, KindResult};
+    use crate::{
+        satif::test::DpllSolver,
+        ts::{TransitionSystem, test::counter},
+    };
+
+    #[test]
+    fn test_kind() {
+        let mut kind = Kind::new(&counter(), DpllSolver::new);
+        assert!(matches!(kind.check(), KindResult::Cex(t) if t.len() == 4));
+
+        let mut ts = TransitionSystem::new();
+        let x = ts.new_latch(Some(false));
+        let y = ts.new_latch(Some(false));
+        ts.set_next(x, y.lit());
+        ts.set_next(y, y.lit());
+        ts.bad.push(x.lit());
+        let mut kind = Kind::new(&ts, DpllSolver::new);
+        assert_eq!(kind.check(), KindResult::Proved(2));
+
+        let mut ts = TransitionSystem::new();
+        let z = ts.new_latch(Some(false));
+        let i = ts.new_input();
+        ts.set_next(z, z.lit());
+        let bad = ts.rel.new_and([z, i]);
+        ts.bad.push(bad);
+        let mut kind = Kind::new(&ts, DpllSolver::new);
+        assert_eq!(kind.check(), KindResult::Proved(1));
+        let mut kind = Kind::new(&ts, DpllSolver::new);
+        kind.set_config(KindConfig {
+            max_k: 5,
+            simple_path: false,
+        });
+        assert_eq!(kind.check(), KindResult::Unknown(5));
+    }
+}
@@ -0,0 +1,258 @@
+mod aiger;
+mod bmc;
+mod certify;
+mod ic3;
+mod kind;
+mod trace;
+mod unroll;
+mod witness;
+
+pub use bmc::*;
+pub use certify::*;
+pub use ic3::*;
+pub use kind::*;
+pub use trace::*;
+pub use unroll::*;
+pub use witness::*;
+
+use crate::{DagCnf, Lit, LitVec, Var, VarLMap, VarSymbols, VarVMap};
+use giputils::hash::{GHashMap, GHashSet};
+use std::iter::once;
+
+/// A sequential model: combinational logic in `rel`, whose leaves are the
+/// inputs and latches.
+#[derive(Clone, Debug, Default)]
+pub struct TransitionSystem {
+    pub rel: DagCnf,
+    pub input: Vec<Var>,
+    pub latch: Vec<Var>,
+    /// Next-state literal of each latch.
+    pub next: VarLMap,
+    /// Initial value of the initialized latches.
+    pub init: GHashMap<Var, bool>,
+    pub constraint: LitVec,
+    pub bad: LitVec,
+    pub justice: Vec<LitVec>,
+    pub fairness: LitVec,
+    pub symbols: VarSymbols,
+}
+
+impl TransitionSystem {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    pub fn max_var(&self) -> Var {
+        self.rel.max_var()
+    }
+
+    #[inline]
+    pub fn new_input(&mut self) -> Var {
+        let v = self.rel.new_var();
+        self.input.push(v);
+        v
+    }
+
+    /// New latch with the given initial value, its next-state literal is set
+    /// with `set_next`.
+    #[inline]
+    pub fn new_latch(&mut self, init: Option<bool>) -> Var {
+        let v = self.rel.new_var();
+        self.latch.push(v);
+        if let Some(i) = init {
+            self.init.insert(v, i);
+        }
+        v
+    }
+
+    #[inline]
+    pub fn set_next(&mut self, latch: Var, next: Lit) {
+        assert!(self.rel.is_leaf(latch));
+        self.next.insert(latch, next);
+    }
+
+    #[inline]
+    pub fn is_latch(&self, v: Var) -> bool {
+        self.latch.contains(&v)
+    }
+
+    /// Next-state literal of a latch literal.
+    #[inline]
+    pub fn next_lit(&self, l: Lit) -> Lit {
+        self.next.map_lit(l).unwrap()
+    }
+
+    /// Literals of the initial values, in latch order.
+    pub fn init_cube(&self) -> LitVec {
+        self.latch
+            .iter()
+            .filter_map(|l| self.init.get(l).map(|&i| l.lit().not_if(!i)))
+            .collect()
+    }
+
+    /// Vars that the properties and constraints depend on, through the logic
+    /// and the next-state functions.
+    pub fn coi_vars(&self) -> GHashSet<Var> {
+        let roots = self
+            .bad
+            .iter()
+            .chain(self.constraint.iter())
+            .chain(self.justice.iter().flatten())
+            .chain(self.fairness.iter())
+            .map(|l| l.var());
+        let mut cone = self.rel.fanins(roots);
+        loop {
+            let next: Vec<Var> = cone
+                .iter()
+                .filter_map(|v| self.next.map(*v))
+                .map(|l| l.var())
+                .filter(|v| !cone.contains(v))
+                .collect();
+            if next.is_empty() {
+                break;
+            }
+            cone.extend(self.rel.fanins(next));
+        }
+        cone.insert(Var::CONST);
+        cone
+    }
+
+    /// Cone-of-influence reduction. Vars keep their numbers, the relations
+    /// of vars outside the cone are dropped.
+    pub fn coi(&self) -> Self {
+        let cone = self.coi_vars();
+        let mut rel = DagCnf::new();
+        rel.new_var_to(self.max_var());
+        for v in self.rel.var_iter_woc() {
+            if cone.contains(&v) && !self.rel.is_leaf(v) {
+                rel.add_rel(v, &self.rel[v]);
+            }
+        }
+        let mut res = Self {
+            rel,
+            input: self.input.clone(),
+            latch: self.latch.clone(),
+            next: self.next.clone(),
+            init: self.init.clone(),
+            constraint: self.constraint.clone(),
+            bad: self.bad.clone(),
+            justice: self.justice.clone(),
+            fairness: self.fairness.clone(),
+            symbols: self.symbols.map_var(|v| cone.contains(&v).then_some(v)),
+        };
+        res.input.retain(|v| cone.contains(v));
+        res.latch.retain(|v| cone.contains(v));
+        res.next.retain(|v, _| cone.contains(v));
+        res.init.retain(|v, _| cone.contains(v));
+        res
+    }
+
+    /// Renumber the vars compactly: inputs first, then latches, then the
+    /// other vars in their original order. Vars that are neither inputs nor
+    /// latches nor used by any relation are dropped. Returns the map from old
+    /// to new vars.
+    pub fn rearrange(&mut self) -> VarVMap {
+        let mut used = GHashSet::new();
+        for cls in self.rel.clause() {
+            for l in cls.iter() {
+                used.insert(l.var());
+            }
+        }
+        for l in self
+            .next
+            .values()
+            .chain(self.bad.iter())
+            .chain(self.constraint.iter())
+            .chain(self.justice.iter().flatten())
+            .chain(self.fairness.iter())
+        {
+            used.insert(l.var());
+        }
+        let mut domain: Vec<Var> = once(Var::CONST)
+            .chain(self.input.iter().copied())
+            .chain(self.latch.iter().copied())
+            .collect();
+        let fixed = GHashSet::from_iter(domain.iter().copied());
+        let mut rest: Vec<Var> = used.into_iter().filter(|v| !fixed.contains(v)).collect();
+        rest.sort();
+        domain.extend(rest);
+        let mut map = VarVMap::new();
+        for (i, &d) in domain.iter().enumerate() {
+            map.insert(d, Var::new(i));
+        }
+        let mut rel = DagCnf::new();
+        rel.new_var_to(Var::new(domain.len() - 1));
+        for &d in domain.iter().skip(1) {
+            if !self.rel.is_leaf(d) {
+                let cls: Vec<_> = self.rel[d].iter().map(|c| c.map_var(|v| map[v])).collect();
+                rel.add_rel(map[d], &cls);
+            }
+        }
+        let ml = |l: &Lit| map.lit_map(*l).unwrap();
+        let mut next = VarLMap::new();
+        for (l, n) in self.next.iter() {
+            next.insert(map[*l], ml(n));
+        }
+        self.rel = rel;
+        self.input = self.input.iter().map(|v| map[*v]).collect();
+        self.latch = self.latch.iter().map(|v| map[*v]).collect();
+        self.next = next;
+        self.init = self.init.iter().map(|(v, i)| (map[*v], *i)).collect();
+        self.constraint = self.constraint.iter().map(ml).collect();
+        self.bad = self.bad.iter().map(ml).collect();
+        self.justice = self
+            .justice
+            .iter()
+            .map(|j| j.iter().map(ml).collect())
+            .collect();
+        self.fairness = self.fairness.iter().map(ml).collect();
+        self.symbols = self.symbols.map_var(map.try_map_fn());
+        map
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::TransitionSystem;
+    use crate::{Lit, LitVec, Var};
+
+    /// 2-bit counter with an enable input, bad when both bits are set, plus
+    /// an unrelated latch.
+    pub(crate) fn counter() -> TransitionSystem {
+        let mut ts = TransitionSystem::new();
+        let en = ts.new_input().lit();
+        let b0 = ts.new_latch(Some(false)).lit();
+        let b1 = ts.new_latch(Some(false)).lit();
+        let u = ts.new_latch(None).lit();
+        let n0 = ts.rel.new_xor(b0, en);
+        let c = ts.rel.new_and([b0, en]);
+        let n1 = ts.rel.new_xor(b1, c);
+        ts.set_next(b0.var(), n0);
+        ts.set_next(b1.var(), n1);
+        ts.set_next(u.var(), !u);
+        let bad = ts.rel.new_and([b0, b1]);
+        ts.bad.push(bad);
+        ts.symbols.insert(en.var(), "en".to_string(), 0);
+        ts.symbols.insert(b0.var(), "cnt".to_string(), 0);
+        ts.symbols.insert(b1.var(), "cnt".to_string(), 1);
+        ts
+    }
+
+    #[test]
+    fn test_coi_rearrange() {
+        let ts = counter();
+        let mut coi = ts.coi();
+        assert_eq!(coi.latch, vec![Var(2), Var(3)]);
+        assert_eq!(
+            coi.init_cube(),
+            LitVec::from([Lit::from(-2), Lit::from(-3)])
+        );
+        let map = coi.rearrange();
+        assert_eq!(map[Var(5)], Var(4));
+        assert_eq!(coi.max_var(), Var(7));
+        assert_eq!(coi.next_lit(Var(2).lit()).var(), Var(4));
+        assert_eq!(coi.symbols.get(Var(3)), vec![("cnt".to_string(), 1)]);
+    }
+}
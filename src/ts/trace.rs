@@ -0,0 +1,64 @@
+use super::TransitionSystem;
+use crate::{Lbool, LboolVec, Lit, LitVec, SymbolAssign, Var, VarSymbols, satif::Satif};
+use giputils::hash::GHashMap;
+
+/// Counterexample of a `TransitionSystem`: the input and latch assignments
+/// of every frame, over the vars of the system.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Trace {
+    pub input: Vec<LitVec>,
+    pub state: Vec<LitVec>,
+}
+
+impl Trace {
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.state.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.state.is_empty()
+    }
+
+    /// Read the frame from a model, `map` gives the copy of a literal in the
+    /// solver.
+    pub(crate) fn push_model(
+        &mut self,
+        ts: &TransitionSystem,
+        solver: &impl Satif,
+        map: impl Fn(Lit) -> Lit,
+    ) {
+        let value = |vs: &[Var]| {
+            vs.iter()
+                .filter_map(|v| solver.sat_value(map(v.lit())).map(|b| v.lit().not_if(!b)))
+                .collect()
+        };
+        self.input.push(value(&ts.input));
+        self.state.push(value(&ts.latch));
+    }
+
+    /// Assignments of the named vars of frame `k`, sorted by name. Bits
+    /// without a value are unknown.
+    pub fn symbol_assigns(&self, symbols: &VarSymbols, k: usize) -> Vec<SymbolAssign> {
+        let mut bits: GHashMap<String, Vec<(usize, bool)>> = GHashMap::new();
+        for l in self.input[k].iter().chain(self.state[k].iter()) {
+            for (s, idx) in symbols.get(l.var()) {
+                bits.entry(s).or_default().push((idx, l.polarity()));
+            }
+        }
+        let mut res: Vec<_> = bits
+            .into_iter()
+            .map(|(symbol, bits)| {
+                let width = bits.iter().map(|(i, _)| i + 1).max().unwrap();
+                let mut assign = LboolVec::from_elem(Lbool::NONE, width);
+                for (i, b) in bits {
+                    assign.set_bool(i, b);
+                }
+                SymbolAssign { symbol, assign }
+            })
+            .collect();
+        res.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+        res
+    }
+}
@@ -0,0 +1,112 @@
+use super::TransitionSystem;
+use crate::{DagCnf, Lit, LitVec, LitVvec, Var, VarRange, VarVMap, satif::Satif};
+
+/// Time-frame expansion of a `TransitionSystem` into a single `DagCnf`.
+/// Frame `k` maps every var of the system to its copy at time `k`; the
+/// latches of frame `k + 1` are defined as the next-state literals of frame
+/// `k`, the latches of frame 0 are free.
+#[derive(Clone, Debug)]
+pub struct TsUnroll {
+    pub ts: TransitionSystem,
+    pub dc: DagCnf,
+    frames: Vec<VarVMap>,
+}
+
+impl TsUnroll {
+    pub fn new(ts: TransitionSystem) -> Self {
+        Self {
+            ts,
+            dc: DagCnf::new(),
+            frames: Vec::new(),
+        }
+    }
+
+    #[inline]
+    pub fn num_frame(&self) -> usize {
+        self.frames.len()
+    }
+
+    #[inline]
+    pub fn frame(&self, k: usize) -> &VarVMap {
+        &self.frames[k]
+    }
+
+    /// Add the next time frame.
+    pub fn unroll(&mut self) {
+        let mut map = VarVMap::new();
+        map.insert(Var::CONST, Var::CONST);
+        let prev = self.frames.last();
+        for v in self.ts.rel.var_iter_woc() {
+            let n = self.dc.new_var();
+            map.insert(v, n);
+            if let Some(prev) = prev
+                && let Some(next) = self.ts.next.map(v)
+            {
+                let next = prev.lit_map(next).unwrap();
+                self.dc.add_rel(n, &LitVvec::cnf_assign(n.lit(), next));
+            } else if !self.ts.rel.is_leaf(v) {
+                let rel: Vec<_> = self.ts.rel[v]
+                    .iter()
+                    .map(|c| c.map_var(|v| map[v]))
+                    .collect();
+                self.dc.add_rel(n, &rel);
+            }
+        }
+        self.frames.push(map);
+    }
+
+    /// Unroll until frame `k` exists.
+    #[inline]
+    pub fn unroll_to(&mut self, k: usize) {
+        while self.frames.len() <= k {
+            self.unroll();
+        }
+    }
+
+    /// Copy of `lit` at frame `k`.
+    #[inline]
+    pub fn lit(&self, lit: Lit, k: usize) -> Lit {
+        self.frames[k].lit_map(lit).unwrap()
+    }
+
+    #[inline]
+    pub fn lits(&self, lits: &[Lit], k: usize) -> LitVec {
+        lits.iter().map(|l| self.lit(*l, k)).collect()
+    }
+
+    /// Add the relations of the vars after `from` to `solver`, returns the
+    /// max var loaded.
+    pub fn load(&self, solver: &mut impl Satif, from: Var) -> Var {
+        let max_var = self.dc.max_var();
+        solver.new_var_to(max_var);
+        for v in VarRange::new_inclusive(from + 1, max_var) {
+            for cls in self.dc[v].iter() {
+                solver.add_clause(cls);
+            }
+        }
+        max_var
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::TsUnroll;
+    use crate::{Lit, Var, dagcnf::gate::Gate, ts::test::counter};
+
+    #[test]
+    fn test_unroll() {
+        let ts = counter();
+        let b0 = ts.latch[0].lit();
+        let mut u = TsUnroll::new(ts.clone());
+        u.unroll_to(2);
+        assert_eq!(u.num_frame(), 3);
+        assert_eq!(u.dc.max_var(), Var(24));
+        let n0 = ts.next_lit(b0);
+        for k in 1..3 {
+            let g = u.dc.gate(u.lit(b0, k).var());
+            assert_eq!(g, Some(Gate::Equiv(u.lit(n0, k - 1))));
+        }
+        assert!(u.dc.is_leaf(u.lit(b0, 0).var()));
+        assert_eq!(u.lit(Lit::constant(true), 2), Lit::constant(true));
+    }
+}
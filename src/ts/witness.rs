@@ -0,0 +1,215 @@
+use super::{Trace, TransitionSystem};
+use crate::{Lbool, Lit, LitVec, Var, VarAssign, VarSymbols};
+use giputils::hash::GHashMap;
+use std::{
+    fmt::Write as _,
+    fs::{File, read_to_string},
+    io::Write,
+    path::Path,
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReplayResult {
+    /// Bad property `bad` is true at frame `frame`.
+    Fired {
+        frame: usize,
+        bad: usize,
+    },
+    /// A constraint is false at frame `frame`, before any bad fires.
+    ConstraintViolated(usize),
+    NotFired,
+}
+
+impl Trace {
+    /// HWMCC AIGER witness for bad property `bad`, values missing in the
+    /// trace are written as `x`. Only the first state is written, the others
+    /// follow from the inputs.
+    pub fn to_aiger_witness(&self, ts: &TransitionSystem, bad: usize) -> String {
+        let line = |vs: &[Var], lits: &LitVec| -> String {
+            let val: GHashMap<_, _> = lits.iter().map(|l| (l.var(), l.polarity())).collect();
+            vs.iter()
+                .map(|v| match val.get(v) {
+                    Some(true) => '1',
+                    Some(false) => '0',
+                    None => 'x',
+                })
+                .collect()
+        };
+        let mut s = format!("1\nb{bad}\n");
+        let init = self.state.first().cloned().unwrap_or_default();
+        writeln!(s, "{}", line(&ts.latch, &init)).unwrap();
+        for input in self.input.iter() {
+            writeln!(s, "{}", line(&ts.input, input)).unwrap();
+        }
+        s.push_str(".\n");
+        s
+    }
+
+    pub fn to_aiger_witness_file<P: AsRef<Path>>(
+        &self,
+        ts: &TransitionSystem,
+        bad: usize,
+        file: P,
+    ) {
+        let mut file = File::create(file).unwrap();
+        file.write_all(self.to_aiger_witness(ts, bad).as_bytes())
+            .unwrap();
+    }
+
+    /// The trace with the named vars of every frame, one assignment per line.
+    pub fn symbol_display(&self, symbols: &VarSymbols) -> String {
+        let mut s = String::new();
+        for k in 0..self.len() {
+            writeln!(s, "frame {k}:").unwrap();
+            for sa in self.symbol_assigns(symbols, k) {
+                writeln!(s, "  {sa}").unwrap();
+            }
+        }
+        s
+    }
+}
+
+impl TransitionSystem {
+    /// Simulate from the first state of `trace` under its inputs, with
+    /// three-valued logic for the missing values. Initialized latches take
+    /// their initial values. Returns the trace with the simulated states.
+    pub fn simulate_trace(&self, trace: &Trace) -> (ReplayResult, Trace) {
+        let mut res = ReplayResult::NotFired;
+        let mut state = VarAssign::new_with(self.max_var());
+        if let Some(s) = trace.state.first() {
+            for &l in s.iter() {
+                state.set(l);
+            }
+        }
+        for l in self.init_cube() {
+            state.set(l);
+        }
+        let mut sim_trace = Trace::default();
+        for (k, input) in trace.input.iter().enumerate() {
+            let mut assign = VarAssign::new_with(self.max_var());
+            let latch: LitVec = self.latch.iter().filter_map(|l| state.vl(*l)).collect();
+            for &l in latch.iter().chain(input.iter()) {
+                assign.set(l);
+            }
+            sim_trace.state.push(latch);
+            sim_trace.input.push(input.clone());
+            let sim = self.rel.ternary_simulation(&assign);
+            let val = |l: Lit| sim.val(l).get(0);
+            if res == ReplayResult::NotFired {
+                if self.constraint.iter().any(|c| val(*c) == Lbool::FALSE) {
+                    res = ReplayResult::ConstraintViolated(k);
+                } else if let Some(bad) = self.bad.iter().position(|b| val(*b) == Lbool::TRUE) {
+                    res = ReplayResult::Fired { frame: k, bad };
+                }
+            }
+            let mut next = VarAssign::new_with(self.max_var());
+            for l in self.latch.iter() {
+                match self.next.map(*l).and_then(|n| Option::<bool>::from(val(n))) {
+                    Some(b) => next.set(l.lit().not_if(!b)),
+                    None => next.set_none(*l),
+                }
+            }
+            state = next;
+        }
+        (res, sim_trace)
+    }
+
+    /// Replay `trace` and check where a bad property fires.
+    #[inline]
+    pub fn replay(&self, trace: &Trace) -> ReplayResult {
+        self.simulate_trace(trace).0
+    }
+
+    /// Read an HWMCC AIGER witness. Returns None if it does not claim a
+    /// counterexample, otherwise the indices of the violated bad properties
+    /// and the trace with the simulated states. Violated justice properties
+    /// are not reported.
+    pub fn read_aiger_witness(&self, witness: &str) -> Option<(Vec<usize>, Trace)> {
+        let mut lines = witness
+            .lines()
+            .map(|l| l.trim())
+            .filter(|l| !l.is_empty() && !l.starts_with('c'));
+        if lines.next()? != "1" {
+            return None;
+        }
+        let bad = lines
+            .next()
+            .unwrap()
+            .split_whitespace()
+            .filter_map(|p| p.strip_prefix('b'))
+            .map(|p| p.parse().unwrap())
+            .collect();
+        let parse = |vs: &[Var], line: &str| -> LitVec {
+            assert_eq!(line.len(), vs.len());
+            vs.iter()
+                .zip(line.chars())
+                .filter_map(|(v, c)| match c {
+                    '0' => Some(!v.lit()),
+                    '1' => Some(v.lit()),
+                    'x' => None,
+                    _ => panic!("invalid witness value {c}"),
+                })
+                .collect()
+        };
+        let mut trace = Trace::default();
+        let init = if self.latch.is_empty() {
+            ""
+        } else {
+            lines.next().unwrap()
+        };
+        trace.state.push(parse(&self.latch, init));
+        for line in lines {
+            if line == "." {
+                break;
+            }
+            trace.input.push(parse(&self.input, line));
+        }
+        Some((bad, self.simulate_trace(&trace).1))
+    }
+
+    pub fn read_aiger_witness_file<P: AsRef<Path>>(&self, file: P) -> Option<(Vec<usize>, Trace)> {
+        self.read_aiger_witness(&read_to_string(file).unwrap())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        LitVec,
+        satif::test::DpllSolver,
+        ts::{Bmc, BmcResult, ReplayResult, TransitionSystem, test::counter},
+    };
+
+    #[test]
+    fn test_witness() {
+        let ts = counter();
+        let BmcResult::Cex(trace) = Bmc::new(&ts, DpllSolver::new()).check(5) else {
+            panic!()
+        };
+        assert_eq!(ts.replay(&trace), ReplayResult::Fired { frame: 3, bad: 0 });
+        let w = "1\nb0\n00x\n1\n1\n1\n0\n.\n";
+        let (bad, rtrace) = ts.read_aiger_witness(w).unwrap();
+        assert_eq!(bad, vec![0]);
+        assert_eq!(rtrace.to_aiger_witness(&ts, 0), w);
+        assert_eq!(ts.replay(&rtrace), ReplayResult::Fired { frame: 3, bad: 0 });
+        let display = rtrace.symbol_display(&ts.symbols);
+        assert!(display.ends_with("frame 3:\n  cnt = 11\n  en = 0\n"));
+        let mut short = rtrace.clone();
+        short.input.truncate(2);
+        assert_eq!(ts.replay(&short), ReplayResult::NotFired);
+    }
+
+    #[test]
+    fn test_witness_free_latch() {
+        let ts = counter();
+        let w = "1\nb0 j0\n00x\n1\n1\n1\n0\n.\n";
+        assert_eq!(ts.read_aiger_witness(w).unwrap().0, vec![0]);
+        let mut ts = TransitionSystem::new();
+        let l = ts.new_latch(Some(true));
+        ts.new_input();
+        ts.bad.push(!l.lit());
+        let (_, trace) = ts.read_aiger_witness("1\nb0\n1\n0\n0\n.\n").unwrap();
+        assert_eq!(trace.state[0], LitVec::from([l.lit()]));
+        assert!(trace.state[1].is_empty());
+    }
+}